@@ -1,5 +1,7 @@
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use k8s_openapi::schemars;
+use std::collections::BTreeMap;
 
 #[derive(Clone, Debug, PartialEq, schemars::JsonSchema, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -36,7 +38,9 @@ impl k8s_openapi::DeepMerge for ProjectResourceQuota {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, schemars::JsonSchema, serde::Deserialize, serde::Serialize)]
+#[derive(
+    Clone, Debug, Default, PartialEq, schemars::JsonSchema, serde::Deserialize, serde::Serialize,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceQuotaLimit {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -65,25 +69,29 @@ pub struct ResourceQuotaLimit {
     pub limits_cpu: Option<Quantity>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limits_memory: Option<Quantity>,
+    /// All the other resources, like `count/deployments.apps` or
+    /// `requests.nvidia.com/gpu`
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Quantity>,
 }
 
-impl Default for ResourceQuotaLimit {
-    fn default() -> Self {
-        ResourceQuotaLimit {
-            pods: Some(Quantity("0".to_string())),
-            services: Some(Quantity("0".to_string())),
-            replication_controllers: Some(Quantity("0".to_string())),
-            secrets: Some(Quantity("0".to_string())),
-            config_maps: Some(Quantity("0".to_string())),
-            persistent_volume_claims: Some(Quantity("0".to_string())),
-            services_node_ports: Some(Quantity("0".to_string())),
-            services_load_balancers: Some(Quantity("0".to_string())),
-            requests_cpu: Some(Quantity("0".to_string())),
-            requests_memory: Some(Quantity("0".to_string())),
-            requests_storage: Some(Quantity("0".to_string())),
-            limits_cpu: Some(Quantity("0".to_string())),
-            limits_memory: Some(Quantity("0".to_string())),
-        }
+impl ResourceQuotaLimit {
+    /// Returns true when no limit is set
+    pub fn is_empty(&self) -> bool {
+        self.pods.is_none()
+            && self.services.is_none()
+            && self.replication_controllers.is_none()
+            && self.secrets.is_none()
+            && self.config_maps.is_none()
+            && self.persistent_volume_claims.is_none()
+            && self.services_node_ports.is_none()
+            && self.services_load_balancers.is_none()
+            && self.requests_cpu.is_none()
+            && self.requests_memory.is_none()
+            && self.requests_storage.is_none()
+            && self.limits_cpu.is_none()
+            && self.limits_memory.is_none()
+            && self.extra.is_empty()
     }
 }
 
@@ -109,6 +117,13 @@ impl k8s_openapi::DeepMerge for ResourceQuotaLimit {
         self.requests_storage.merge_from(other.requests_storage);
         self.limits_cpu.merge_from(other.limits_cpu);
         self.limits_memory.merge_from(other.limits_memory);
+        k8s_openapi::merge_strategies::map::granular(
+            &mut self.extra,
+            other.extra,
+            |current_item, other_item| {
+                current_item.merge_from(other_item);
+            },
+        );
     }
 }
 
@@ -123,6 +138,20 @@ pub struct ContainerResourceLimit {
     pub limits_cpu: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limits_memory: Option<String>,
+    /// All the other resources, like `requestsEphemeralStorage`
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl ContainerResourceLimit {
+    /// Returns true when no default is set
+    pub fn is_empty(&self) -> bool {
+        self.requests_cpu.is_none()
+            && self.requests_memory.is_none()
+            && self.limits_cpu.is_none()
+            && self.limits_memory.is_none()
+            && self.extra.is_empty()
+    }
 }
 
 impl k8s_openapi::DeepMerge for ContainerResourceLimit {
@@ -134,30 +163,130 @@ impl k8s_openapi::DeepMerge for ContainerResourceLimit {
         self.requests_memory.merge_from(other.requests_memory);
         self.limits_cpu.merge_from(other.limits_cpu);
         self.limits_memory.merge_from(other.limits_memory);
+        k8s_openapi::merge_strategies::map::granular(
+            &mut self.extra,
+            other.extra,
+            |current_item, other_item| {
+                current_item.merge_from(other_item);
+            },
+        );
+    }
+}
+
+/// A Rancher Project, `management.cattle.io/v3`
+///
+/// The type is written by hand, instead of being generated by
+/// `k8s_openapi_derive`, to be tolerant with the different shapes of the
+/// resource created by the Rancher releases: missing fields get their
+/// default value, unknown fields are preserved and the status is kept.
+#[derive(Clone, Debug, Default, PartialEq, schemars::JsonSchema, serde::Deserialize)]
+#[serde(default)]
+pub struct Project {
+    pub metadata: ObjectMeta,
+    pub spec: Option<ProjectSpec>,
+    pub status: Option<ProjectStatus>,
+    /// Top-level fields not known by the policy, added by other Rancher
+    /// releases
+    #[serde(flatten, deserialize_with = "deserialize_project_extra")]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// The `apiVersion` and `kind` fields are not kept among the unknown fields,
+/// they are always serialized from the type
+fn deserialize_project_extra<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, serde_json::Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut extra: BTreeMap<String, serde_json::Value> =
+        serde::Deserialize::deserialize(deserializer)?;
+    extra.remove("apiVersion");
+    extra.remove("kind");
+    Ok(extra)
+}
+
+impl k8s_openapi::Resource for Project {
+    const API_VERSION: &'static str = "management.cattle.io/v3";
+    const GROUP: &'static str = "management.cattle.io";
+    const KIND: &'static str = "Project";
+    const VERSION: &'static str = "v3";
+    const URL_PATH_SEGMENT: &'static str = "projects";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl k8s_openapi::ListableResource for Project {
+    const LIST_KIND: &'static str = "ProjectList";
+}
+
+impl k8s_openapi::Metadata for Project {
+    type Ty = ObjectMeta;
+
+    fn metadata(&self) -> &<Self as k8s_openapi::Metadata>::Ty {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut <Self as k8s_openapi::Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl k8s_openapi::DeepMerge for Project {
+    fn merge_from(&mut self, other: Self)
+    where
+        Self: Sized,
+    {
+        self.metadata.merge_from(other.metadata);
+        self.spec.merge_from(other.spec);
+        self.status.merge_from(other.status);
+        k8s_openapi::merge_strategies::map::granular(
+            &mut self.extra,
+            other.extra,
+            |current_item, other_item| {
+                *current_item = other_item;
+            },
+        );
+    }
+}
+
+impl serde::Serialize for Project {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use k8s_openapi::Resource;
+        use serde::ser::SerializeMap;
+
+        // a map is used instead of a struct to serialize the unknown fields
+        let mut state = serializer.serialize_map(Some(
+            3 + self.spec.as_ref().map_or(0, |_| 1)
+                + self.status.as_ref().map_or(0, |_| 1)
+                + self.extra.len(),
+        ))?;
+        state.serialize_entry("apiVersion", Self::API_VERSION)?;
+        state.serialize_entry("kind", Self::KIND)?;
+        state.serialize_entry("metadata", &self.metadata)?;
+        if let Some(spec) = &self.spec {
+            state.serialize_entry("spec", spec)?;
+        }
+        if let Some(status) = &self.status {
+            state.serialize_entry("status", status)?;
+        }
+        for (key, value) in &self.extra {
+            state.serialize_entry(key, value)?;
+        }
+        state.end()
     }
 }
 
 #[derive(
-    Clone,
-    Debug,
-    PartialEq,
-    k8s_openapi_derive::CustomResourceDefinition,
-    schemars::JsonSchema,
-    serde::Deserialize,
-    serde::Serialize,
-)]
-#[custom_resource_definition(
-    group = "management.cattle.io",
-    version = "v3",
-    plural = "projects",
-    generate_schema,
-    namespaced,
-    impl_deep_merge
+    Clone, Debug, Default, PartialEq, schemars::JsonSchema, serde::Deserialize, serde::Serialize,
 )]
 #[serde(rename_all = "camelCase")]
 pub struct ProjectSpec {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub display_name: Option<String>,
+    #[serde(default)]
     pub description: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cluster_name: Option<String>,
@@ -167,7 +296,11 @@ pub struct ProjectSpec {
     pub namespace_default_resource_quota: Option<NamespaceResourceQuota>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub container_default_resource_limit: Option<ContainerResourceLimit>,
+    #[serde(default)]
     pub enable_project_monitoring: bool,
+    /// Fields not known by the policy, added by other Rancher releases
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 impl k8s_openapi::DeepMerge for ProjectSpec {
@@ -185,5 +318,249 @@ impl k8s_openapi::DeepMerge for ProjectSpec {
             .merge_from(other.container_default_resource_limit);
         self.enable_project_monitoring
             .merge_from(other.enable_project_monitoring);
+        k8s_openapi::merge_strategies::map::granular(
+            &mut self.extra,
+            other.extra,
+            |current_item, other_item| {
+                *current_item = other_item;
+            },
+        );
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, PartialEq, schemars::JsonSchema, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStatus {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<ProjectCondition>,
+    /// Namespace holding the resources of the Project, set by Rancher 2.8+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backing_namespace: Option<String>,
+    /// Fields not known by the policy, like the `podSecurityPolicyTemplateId`
+    /// of the old Rancher releases
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl ProjectStatus {
+    /// Find the condition of the given type
+    pub fn condition(&self, type_: &str) -> Option<&ProjectCondition> {
+        self.conditions
+            .iter()
+            .find(|condition| condition.type_ == type_)
+    }
+}
+
+impl k8s_openapi::DeepMerge for ProjectStatus {
+    fn merge_from(&mut self, other: Self)
+    where
+        Self: Sized,
+    {
+        k8s_openapi::merge_strategies::list::atomic(&mut self.conditions, other.conditions);
+        self.backing_namespace.merge_from(other.backing_namespace);
+        k8s_openapi::merge_strategies::map::granular(
+            &mut self.extra,
+            other.extra,
+            |current_item, other_item| {
+                *current_item = other_item;
+            },
+        );
+    }
+}
+
+/// A condition of a Project. Rancher does not use the standard
+/// `metav1.Condition`, its conditions have a `lastUpdateTime` and most of the
+/// fields are optional
+#[derive(
+    Clone, Debug, Default, PartialEq, schemars::JsonSchema, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectCondition {
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// One of `True`, `False` or `Unknown`
+    #[serde(default)]
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_update_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_transition_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl ProjectCondition {
+    /// Returns true when the status of the condition is `True`
+    pub fn is_true(&self) -> bool {
+        self.status == "True"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::DeepMerge;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case::rancher_2_6(
+        include_str!("../test_data/projects/rancher-2.6.json"),
+        "A test project used to play with labels",
+        None,
+        3,
+        &[]
+    )]
+    #[case::rancher_2_7(
+        include_str!("../test_data/projects/rancher-2.7.json"),
+        "",
+        None,
+        3,
+        &["nodeSelector"]
+    )]
+    #[case::rancher_2_8(
+        include_str!("../test_data/projects/rancher-2.8.json"),
+        "Cost center 1234",
+        Some("local-p-m9z4t"),
+        3,
+        &[]
+    )]
+    #[case::minimal(include_str!("../test_data/projects/minimal.json"), "", None, 0, &[])]
+    fn deserialize_project(
+        #[case] raw: &str,
+        #[case] description: &str,
+        #[case] backing_namespace: Option<&str>,
+        #[case] conditions: usize,
+        #[case] extra: &[&str],
+    ) {
+        let project: Project = serde_json::from_str(raw).expect("cannot deserialize Project");
+
+        let spec = project.spec.as_ref().expect("spec is missing");
+        assert_eq!(description, spec.description);
+        assert!(!spec.enable_project_monitoring);
+        assert!(project
+            .metadata
+            .labels
+            .as_ref()
+            .expect("labels are missing")
+            .keys()
+            .any(|key| key.starts_with("propagate.")));
+
+        let status = project.status.clone().unwrap_or_default();
+        assert_eq!(backing_namespace, status.backing_namespace.as_deref());
+        assert_eq!(conditions, status.conditions.len());
+        assert_eq!(
+            extra,
+            project.extra.keys().map(String::as_str).collect::<Vec<_>>()
+        );
+
+        // serializing the Project must not lose any field
+        let expected: serde_json::Value = serde_json::from_str(raw).expect("invalid JSON");
+        let mut actual = serde_json::to_value(&project).expect("cannot serialize Project");
+        // the defaults of the missing fields are added
+        for field in ["description", "enableProjectMonitoring"] {
+            if expected["spec"].get(field).is_none() {
+                actual["spec"]
+                    .as_object_mut()
+                    .expect("spec is not an object")
+                    .remove(field);
+            }
+        }
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn project_keeps_unknown_fields() {
+        let project: Project = serde_json::from_value(json!({
+            "apiVersion": "management.cattle.io/v3",
+            "kind": "Project",
+            "metadata": {"name": "p-abc12", "namespace": "local"},
+            "spec": {"clusterName": "local", "newSetting": {"enabled": true}},
+            "status": {"podSecurityPolicyTemplateId": ""},
+            "nodeSelector": {"tier": "frontend"},
+        }))
+        .expect("cannot deserialize Project");
+
+        assert_eq!(
+            BTreeMap::from([("nodeSelector".to_string(), json!({"tier": "frontend"}))]),
+            project.extra
+        );
+
+        let spec = project.spec.expect("spec is missing");
+        assert_eq!(
+            Some(&json!({"enabled": true})),
+            spec.extra.get("newSetting")
+        );
+        let status = project.status.expect("status is missing");
+        assert_eq!(
+            Some(&json!("")),
+            status.extra.get("podSecurityPolicyTemplateId")
+        );
+    }
+
+    #[test]
+    fn resource_quota_limit_keeps_unknown_resources() {
+        let raw = json!({
+            "limitsCpu": "1",
+            "count/deployments.apps": "10",
+            "requests.nvidia.com/gpu": "2",
+        });
+        let limit: ResourceQuotaLimit =
+            serde_json::from_value(raw.clone()).expect("cannot deserialize limit");
+
+        assert_eq!(Some(Quantity("1".to_string())), limit.limits_cpu);
+        assert_eq!(
+            Some(&Quantity("2".to_string())),
+            limit.extra.get("requests.nvidia.com/gpu")
+        );
+        assert!(!limit.is_empty());
+        assert_eq!(raw, serde_json::to_value(&limit).expect("cannot serialize"));
+    }
+
+    #[test]
+    fn container_resource_limit_keeps_unknown_resources() {
+        let raw = json!({
+            "limitsCpu": "20m",
+            "requestsEphemeralStorage": "1Gi",
+        });
+        let limit: ContainerResourceLimit =
+            serde_json::from_value(raw.clone()).expect("cannot deserialize limit");
+
+        assert_eq!(
+            Some(&"1Gi".to_string()),
+            limit.extra.get("requestsEphemeralStorage")
+        );
+        assert_eq!(raw, serde_json::to_value(&limit).expect("cannot serialize"));
+    }
+
+    #[test]
+    fn deep_merge_unknown_resources() {
+        let mut current: ResourceQuotaLimit = serde_json::from_value(json!({
+            "pods": "10",
+            "count/deployments.apps": "10",
+            "count/jobs.batch": "5",
+        }))
+        .expect("cannot deserialize limit");
+        let other: ResourceQuotaLimit = serde_json::from_value(json!({
+            "count/deployments.apps": "20",
+            "requests.ephemeral-storage": "1Gi",
+        }))
+        .expect("cannot deserialize limit");
+
+        current.merge_from(other);
+
+        assert_eq!(
+            json!({
+                "pods": "10",
+                "count/deployments.apps": "20",
+                "count/jobs.batch": "5",
+                "requests.ephemeral-storage": "1Gi",
+            }),
+            serde_json::to_value(&current).expect("cannot serialize")
+        );
     }
 }
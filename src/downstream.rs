@@ -0,0 +1,57 @@
+use k8s_openapi::api::core::v1 as apicore;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kubewarden::host_capabilities::kubernetes::GetResourceRequest;
+use std::collections::BTreeMap;
+
+use crate::custom_resources::Project;
+use crate::errors::{Lookup, PolicyError, PolicyResult};
+use crate::get_resource;
+use crate::project_id::ProjectId;
+use crate::settings::ProjectsMirror;
+
+/// Build the Project a Namespace of a downstream cluster belongs to, starting
+/// from the ConfigMap that mirrors the labels of the Projects defined inside
+/// of the management cluster.
+///
+/// The keys of the ConfigMap are the project ids, the values are the JSON
+/// encoded labels of the Project
+pub(crate) fn fetch_mirrored_project(
+    mirror: &ProjectsMirror,
+    project_id: &ProjectId,
+) -> PolicyResult<Project> {
+    let lookup = Lookup::get_config_map(&mirror.namespace, &mirror.name);
+    let req = GetResourceRequest {
+        api_version: "v1".to_string(),
+        kind: "ConfigMap".to_string(),
+        name: mirror.name.clone(),
+        namespace: Some(mirror.namespace.clone()),
+        disable_cache: true,
+    };
+    let config_map: apicore::ConfigMap =
+        get_resource(&req).map_err(|e| PolicyError::from_lookup(lookup.clone(), e))?;
+
+    let raw_labels = config_map
+        .data
+        .unwrap_or_default()
+        .remove(&project_id.project)
+        .ok_or_else(|| {
+            PolicyError::Validation(format!(
+                "the Project {project_id} is not mirrored by the {lookup}, the mirror is probably out of sync"
+            ))
+        })?;
+    let labels: BTreeMap<String, String> =
+        serde_json::from_str(&raw_labels).map_err(|e| PolicyError::Deserialization {
+            what: format!("the labels of the Project {project_id} mirrored by the {lookup}"),
+            details: e.to_string(),
+        })?;
+
+    Ok(Project {
+        metadata: ObjectMeta {
+            name: Some(project_id.project.clone()),
+            namespace: Some(project_id.cluster_id.clone()),
+            labels: Some(labels),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use crate::errors::PolicyResult;
+use crate::project_id::ProjectId;
+use crate::project_namespaces::list_project_namespaces;
+
+/// Find the Namespaces of the Project whose labels no longer match the ones
+/// propagated by the Project, once its labels are changed.
+///
+/// A Namespace drifts when it lacks one of the `propagated` labels, or when
+/// it still carries one of the `previous` labels that is no longer propagated.
+/// Labels are propagated only when a Namespace is created or updated, these
+/// Namespaces keep their labels until then
+pub(crate) fn drifting_namespaces(
+    project_id: &ProjectId,
+    propagated: &BTreeMap<String, String>,
+    previous: &BTreeMap<String, String>,
+    local_cluster_id: &str,
+) -> PolicyResult<Vec<String>> {
+    Ok(list_project_namespaces(project_id, local_cluster_id)?
+        .into_iter()
+        .filter(|namespace| {
+            let labels = namespace.metadata.labels.clone().unwrap_or_default();
+            is_drifting(&labels, propagated, previous)
+        })
+        .filter_map(|namespace| namespace.metadata.name)
+        .collect())
+}
+
+fn is_drifting(
+    labels: &BTreeMap<String, String>,
+    propagated: &BTreeMap<String, String>,
+    previous: &BTreeMap<String, String>,
+) -> bool {
+    let missing = propagated
+        .iter()
+        .any(|(key, value)| labels.get(key) != Some(value));
+    let stale = previous
+        .iter()
+        .filter(|(key, _)| !propagated.contains_key(*key))
+        .any(|(key, value)| labels.get(key) == Some(value));
+    missing || stale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    fn labels(labels: serde_json::Value) -> BTreeMap<String, String> {
+        serde_json::from_value(labels).expect("cannot deserialize labels")
+    }
+
+    #[rstest]
+    #[case::up_to_date(json!({"team": "payments", "other": "x"}), false)]
+    #[case::changed_value(json!({"team": "billing", "tier": "gold"}), true)]
+    #[case::missing_label(json!({}), true)]
+    #[case::stale_label(json!({"team": "payments", "tier": "gold"}), true)]
+    #[case::removed_label_owned_by_namespace(json!({"team": "payments", "tier": "custom"}), false)]
+    fn drifting(#[case] namespace_labels: serde_json::Value, #[case] expected: bool) {
+        let propagated = labels(json!({"team": "payments"}));
+        let previous = labels(json!({"team": "billing", "tier": "gold"}));
+
+        assert_eq!(
+            expected,
+            is_drifting(&labels(namespace_labels), &propagated, &previous)
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+
+use kubewarden::wapc_guest::CallResult;
+
+use crate::project_id::ProjectIdError;
+use crate::RANCHER_PROJECT_ID_LABEL;
+
+pub(crate) type PolicyResult<T> = std::result::Result<T, PolicyError>;
+
+/// A Kubernetes resource the policy tried to read through the host capabilities
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Lookup {
+    /// The verb required to perform the operation: `get` or `list`
+    pub verb: &'static str,
+    /// API group of the resource, empty for the core group
+    pub api_group: &'static str,
+    /// Plural name of the resource, as used inside of RBAC rules
+    pub resource: &'static str,
+    /// Namespace scoping the lookup, `None` for cluster wide lookups
+    pub namespace: Option<String>,
+    /// Name of the resource, `None` when listing resources
+    pub name: Option<String>,
+}
+
+impl Lookup {
+    pub fn get_project(namespace: &str, name: &str) -> Self {
+        Lookup {
+            verb: "get",
+            api_group: "management.cattle.io",
+            resource: "projects",
+            namespace: Some(namespace.to_owned()),
+            name: Some(name.to_owned()),
+        }
+    }
+
+    pub fn get_config_map(namespace: &str, name: &str) -> Self {
+        Lookup {
+            verb: "get",
+            api_group: "",
+            resource: "configmaps",
+            namespace: Some(namespace.to_owned()),
+            name: Some(name.to_owned()),
+        }
+    }
+
+    pub fn list_projects(namespace: &str) -> Self {
+        Lookup {
+            verb: "list",
+            api_group: "management.cattle.io",
+            resource: "projects",
+            namespace: Some(namespace.to_owned()),
+            name: None,
+        }
+    }
+
+    pub fn list_namespaces() -> Self {
+        Lookup {
+            verb: "list",
+            api_group: "",
+            resource: "namespaces",
+            namespace: None,
+            name: None,
+        }
+    }
+
+    /// Describe the RBAC resources that grant the policy access to the resource
+    fn required_rbac(&self) -> String {
+        let rule = format!(
+            "apiGroups: [\"{}\"], resources: [\"{}\"], verbs: [\"{}\"]",
+            self.api_group, self.resource, self.verb
+        );
+        match &self.namespace {
+            Some(namespace) if self.resource == "projects" => format!(
+                "create the Role `rancher-project-reader` inside of the `{namespace}` Namespace with the rule `{rule}`, \
+                then bind it to the ServiceAccount used by the PolicyServer with the RoleBinding `read-rancher-projects-{namespace}` (see rbac.yaml)"
+            ),
+            Some(namespace) => format!(
+                "create a Role inside of the `{namespace}` Namespace with the rule `{rule}`, \
+                then bind it to the ServiceAccount used by the PolicyServer with a RoleBinding"
+            ),
+            None => format!(
+                "create a ClusterRole with the rule `{rule}`, \
+                then bind it to the ServiceAccount used by the PolicyServer with a ClusterRoleBinding"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Lookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resource = if self.api_group.is_empty() {
+            self.resource.to_owned()
+        } else {
+            format!("{}.{}", self.resource, self.api_group)
+        };
+        match &self.name {
+            Some(name) => write!(f, "{resource} `{name}`")?,
+            None => write!(f, "{resource}")?,
+        }
+        if let Some(namespace) = &self.namespace {
+            write!(f, " inside of the `{namespace}` Namespace")?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors raised while evaluating a request. Each one of them is turned into
+/// a rejection response
+#[derive(Debug)]
+pub(crate) enum PolicyError {
+    /// The value of the `field.cattle.io/projectId` annotation or label cannot be parsed
+    InvalidProjectId {
+        origin: &'static str,
+        value: String,
+        source: ProjectIdError,
+    },
+    /// The resource does not exist
+    NotFound(Lookup),
+    /// The policy is not allowed to read the resource
+    Forbidden { lookup: Lookup, details: String },
+    /// The host could not perform the lookup
+    LookupFailed { lookup: Lookup, details: String },
+    /// An object could not be deserialized
+    Deserialization { what: String, details: String },
+    /// An object could not be serialized
+    Serialization { what: String, details: String },
+    /// The request violates one of the rules enforced by the policy
+    Validation(String),
+}
+
+impl PolicyError {
+    /// Classify an error returned by the Kubernetes host capabilities
+    pub fn from_lookup(lookup: Lookup, error: anyhow::Error) -> Self {
+        let details = error.to_string();
+        let normalized = details.to_lowercase();
+
+        if normalized.contains("error deserializing") {
+            PolicyError::Deserialization {
+                what: lookup.to_string(),
+                details,
+            }
+        } else if normalized.contains("forbidden") || normalized.contains("code: 403") {
+            PolicyError::Forbidden { lookup, details }
+        } else if normalized.contains("not found")
+            || normalized.contains("notfound")
+            || normalized.contains("code: 404")
+        {
+            PolicyError::NotFound(lookup)
+        } else {
+            PolicyError::LookupFailed { lookup, details }
+        }
+    }
+
+    /// The HTTP code returned to the user
+    pub fn code(&self) -> u16 {
+        match self {
+            PolicyError::InvalidProjectId { .. } | PolicyError::Validation(_) => 400,
+            PolicyError::NotFound(_) => 404,
+            PolicyError::Forbidden { .. }
+            | PolicyError::Deserialization { .. }
+            | PolicyError::Serialization { .. } => 500,
+            PolicyError::LookupFailed { .. } => 503,
+        }
+    }
+
+    /// Build the rejection response sent back to the user
+    pub fn reject(&self) -> CallResult {
+        kubewarden::reject_request(Some(self.to_string()), Some(self.code()), None, None)
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::InvalidProjectId {
+                origin,
+                value,
+                source,
+            } => write!(
+                f,
+                "malformed value `{value}` of {RANCHER_PROJECT_ID_LABEL} {origin}: {source}"
+            ),
+            PolicyError::NotFound(lookup) => write!(f, "cannot find {lookup}"),
+            PolicyError::Forbidden { lookup, details } => write!(
+                f,
+                "the policy is not allowed to {} {lookup}: {}. Error: {details}",
+                lookup.verb,
+                lookup.required_rbac()
+            ),
+            PolicyError::LookupFailed { lookup, details } => write!(
+                f,
+                "cannot {} {lookup}, the Kubernetes API server could not be queried: {details}",
+                lookup.verb
+            ),
+            PolicyError::Deserialization { what, details } => {
+                write!(f, "cannot deserialize {what}: {details}")
+            }
+            PolicyError::Serialization { what, details } => {
+                write!(f, "cannot serialize {what}: {details}")
+            }
+            PolicyError::Validation(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use rstest::*;
+
+    #[rstest]
+    #[case(
+        "ApiError: projects.management.cattle.io \"p-5fcf4\" not found: NotFound (ErrorResponse { reason: \"NotFound\", code: 404 })",
+        404
+    )]
+    #[case(
+        "ApiError: projects.management.cattle.io \"p-5fcf4\" is forbidden: User \"system:serviceaccount:kubewarden:policy-server\" cannot get resource \"projects\" (ErrorResponse { reason: \"Forbidden\", code: 403 })",
+        500
+    )]
+    #[case(
+        "error deserializing get resource response into Kubernetes resource: missing field `metadata`",
+        500
+    )]
+    #[case("connection refused", 503)]
+    fn classify_lookup_errors(#[case] error: &str, #[case] expected_code: u16) {
+        let err =
+            PolicyError::from_lookup(Lookup::get_project("local", "p-5fcf4"), anyhow!("{error}"));
+        assert_eq!(expected_code, err.code());
+    }
+
+    #[test]
+    fn forbidden_error_names_rbac_resources() {
+        let err = PolicyError::from_lookup(
+            Lookup::get_project("local", "p-5fcf4"),
+            anyhow!("projects.management.cattle.io \"p-5fcf4\" is forbidden"),
+        );
+        let msg = err.to_string();
+        assert!(msg.contains("`rancher-project-reader`"), "{msg}");
+        assert!(msg.contains("`read-rancher-projects-local`"), "{msg}");
+    }
+}
@@ -0,0 +1,66 @@
+/// Match `value` against a shell-like `pattern`. The `*` wildcard matches any
+/// sequence of characters, including an empty one, while `?` matches exactly
+/// one character. All the other characters are matched literally
+pub(crate) fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    // position of the last `*` seen inside of the pattern, and the position
+    // of the value where its match started
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    // let the last `*` consume one more character
+                    p = star + 1;
+                    v = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Returns true when the pattern contains wildcard characters
+pub(crate) fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("local", "local", true)]
+    #[case("local", "locals", false)]
+    #[case("*", "", true)]
+    #[case("*", "c-m-abcd1234", true)]
+    #[case("c-m-*", "c-m-abcd1234", true)]
+    #[case("c-m-*", "c-abcde", false)]
+    #[case("kube-*", "kube-system", true)]
+    #[case("*-prod-*", "team-prod-eu", true)]
+    #[case("*-prod-*", "team-production", false)]
+    #[case("c-?????", "c-abcde", true)]
+    #[case("c-?????", "c-abcdef", false)]
+    #[case("a*b*c", "aXXbYYbZc", true)]
+    #[case("a*b*c", "aXXbYYbZ", false)]
+    #[case("app.kubernetes.io/*", "app.kubernetes.io/name", true)]
+    fn glob(#[case] pattern: &str, #[case] value: &str, #[case] expected: bool) {
+        assert_eq!(expected, glob_match(pattern, value));
+    }
+}
@@ -0,0 +1,656 @@
+use k8s_openapi::api::core::v1 as apicore;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::custom_resources::Project;
+use crate::errors::{PolicyError, PolicyResult};
+use crate::glob::glob_match;
+use crate::project_id::ProjectId;
+use crate::project_name::PROJECT_NAME_LABEL;
+use crate::settings::{is_dns_subdomain, Settings};
+use crate::RANCHER_PROJECT_ID_LABEL;
+
+/// Prefix of the Project labels that are propagated to the Namespaces
+pub(crate) const PROPAGATE_PREFIX: &str = "propagate.";
+
+/// Label domains reserved by Kubernetes, together with all their subdomains
+const RESERVED_LABEL_DOMAINS: [&str; 2] = ["kubernetes.io", "k8s.io"];
+
+/// Labels of the reserved domains that are meant to be set by users: the
+/// Pod Security Admission ones, which define the security profile of a Namespace
+const USER_SETTABLE_RESERVED_LABELS: [&str; 6] = [
+    "pod-security.kubernetes.io/enforce",
+    "pod-security.kubernetes.io/enforce-version",
+    "pod-security.kubernetes.io/audit",
+    "pod-security.kubernetes.io/audit-version",
+    "pod-security.kubernetes.io/warn",
+    "pod-security.kubernetes.io/warn-version",
+];
+
+/// Namespace labels managed by Rancher or by the policy, that cannot be
+/// overwritten by a propagated label
+const PROTECTED_NAMESPACE_LABELS: [&str; 2] = [RANCHER_PROJECT_ID_LABEL, PROJECT_NAME_LABEL];
+
+/// Project annotation holding the comma separated list of the labels the
+/// Namespaces of the Project can carry, besides the propagated ones. Glob
+/// patterns like `app.kubernetes.io/*` are allowed
+pub(crate) const ALLOWED_NAMESPACE_LABELS_ANNOTATION: &str =
+    "kubewarden.io/allowed-namespace-labels";
+
+/// Namespace labels set by Kubernetes and Rancher, that are always allowed
+const SYSTEM_NAMESPACE_LABELS: [&str; 3] = [
+    "kubernetes.io/metadata.name",
+    RANCHER_PROJECT_ID_LABEL,
+    PROJECT_NAME_LABEL,
+];
+
+/// A Project label that cannot be propagated to the Namespaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LabelViolation {
+    /// The propagated key is not a valid label key
+    InvalidKey { key: String, reason: String },
+    /// The value is not a valid label value
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    /// The propagated key belongs to a reserved domain
+    Reserved { key: String, domain: String },
+    /// The value is not one of the values allowed by the settings
+    NotAllowedValue {
+        key: String,
+        value: String,
+        allowed: Vec<String>,
+    },
+    /// The propagated key would overwrite a label managed by Rancher or by
+    /// the policy
+    Collision { key: String },
+}
+
+impl LabelViolation {
+    /// The propagated key, without the `propagate.` prefix
+    fn key(&self) -> &str {
+        match self {
+            LabelViolation::InvalidKey { key, .. }
+            | LabelViolation::InvalidValue { key, .. }
+            | LabelViolation::Reserved { key, .. }
+            | LabelViolation::NotAllowedValue { key, .. }
+            | LabelViolation::Collision { key } => key,
+        }
+    }
+}
+
+impl fmt::Display for LabelViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LabelViolation::InvalidKey { key, reason } => {
+                write!(f, "`{PROPAGATE_PREFIX}{key}`: invalid label key, {reason}")
+            }
+            LabelViolation::InvalidValue { key, value, reason } => write!(
+                f,
+                "`{PROPAGATE_PREFIX}{key}`: invalid label value `{value}`, {reason}"
+            ),
+            LabelViolation::Reserved { key, domain } => write!(
+                f,
+                "`{PROPAGATE_PREFIX}{key}`: the `{domain}` label domain is reserved"
+            ),
+            LabelViolation::NotAllowedValue {
+                key,
+                value,
+                allowed,
+            } => write!(
+                f,
+                "`{PROPAGATE_PREFIX}{key}`: value `{value}` is not allowed, must be one of: {}",
+                allowed.join(", ")
+            ),
+            LabelViolation::Collision { key } => write!(
+                f,
+                "`{PROPAGATE_PREFIX}{key}`: would overwrite the `{key}` label managed by Rancher or by the policy"
+            ),
+        }
+    }
+}
+
+/// Compute the labels the Project propagates to its Namespaces: the ones
+/// with the `propagate.` prefix, with the prefix removed.
+///
+/// Returns all the labels that cannot be propagated when at least one of
+/// them is invalid, reserved, has a value that is not allowed or would
+/// overwrite a label managed by Rancher or by the policy
+pub(crate) fn propagated_labels(
+    project_labels: &BTreeMap<String, String>,
+    settings: &Settings,
+) -> Result<BTreeMap<String, String>, Vec<LabelViolation>> {
+    let (labels, violations) = partitioned_propagated_labels(project_labels, settings);
+    if violations.is_empty() {
+        Ok(labels)
+    } else {
+        Err(violations)
+    }
+}
+
+/// Same as [`propagated_labels`], returning both the labels that can be
+/// propagated and the ones that cannot
+pub(crate) fn partitioned_propagated_labels(
+    project_labels: &BTreeMap<String, String>,
+    settings: &Settings,
+) -> (BTreeMap<String, String>, Vec<LabelViolation>) {
+    let mut labels = BTreeMap::new();
+    let mut violations = Vec::new();
+
+    for (key, value) in project_labels {
+        let key = match key.strip_prefix(PROPAGATE_PREFIX) {
+            Some(key) => key,
+            None => continue,
+        };
+        match check_label(key, value, settings) {
+            Some(violation) => violations.push(violation),
+            None => {
+                labels.insert(key.to_owned(), value.to_owned());
+            }
+        }
+    }
+    (labels, violations)
+}
+
+/// Same as [`propagated_labels`], turning the violations into a policy error
+pub(crate) fn checked_propagated_labels(
+    project_name: &str,
+    project_labels: &BTreeMap<String, String>,
+    settings: &Settings,
+) -> PolicyResult<BTreeMap<String, String>> {
+    propagated_labels(project_labels, settings).map_err(|violations| {
+        PolicyError::Validation(violations_message(project_name, &violations))
+    })
+}
+
+/// Same as [`checked_propagated_labels`], for the UPDATE of a Project: only
+/// the labels added or changed by the UPDATE are checked. The labels the
+/// Project already had are tolerated, to not block the updates done by
+/// Rancher to the Projects created before the policy was deployed.
+///
+/// Returns `None` when some of the tolerated labels cannot be propagated
+pub(crate) fn checked_changed_propagated_labels(
+    project_name: &str,
+    project_labels: &BTreeMap<String, String>,
+    old_project_labels: &BTreeMap<String, String>,
+    settings: &Settings,
+) -> PolicyResult<Option<BTreeMap<String, String>>> {
+    let violations = match propagated_labels(project_labels, settings) {
+        Ok(labels) => return Ok(Some(labels)),
+        Err(violations) => violations,
+    };
+    let changed: Vec<LabelViolation> = violations
+        .into_iter()
+        .filter(|violation| {
+            let key = format!("{PROPAGATE_PREFIX}{}", violation.key());
+            project_labels.get(&key) != old_project_labels.get(&key)
+        })
+        .collect();
+    if changed.is_empty() {
+        return Ok(None);
+    }
+    Err(PolicyError::Validation(violations_message(
+        project_name,
+        &changed,
+    )))
+}
+
+/// Describe the labels of the Project that cannot be propagated
+pub(crate) fn violations_message(project_name: &str, violations: &[LabelViolation]) -> String {
+    format!(
+        "the Project {project_name} has labels that cannot be propagated: {}",
+        violations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    )
+}
+
+/// Ensure the Namespace carries only the labels allowed by its Project: the
+/// propagated ones, the ones set by Kubernetes and Rancher and the ones
+/// matching the `kubewarden.io/allowed-namespace-labels` annotation of the
+/// Project. Projects without the annotation allow any label.
+///
+/// Labels already carried by the old Namespace, with the same value, are not
+/// checked
+pub(crate) fn check_namespace_labels(
+    project_id: &ProjectId,
+    project: &Project,
+    namespace: &apicore::Namespace,
+    old_namespace: Option<&apicore::Namespace>,
+    propagated_labels: &BTreeMap<String, String>,
+) -> PolicyResult<()> {
+    let allowlist: Vec<&str> = match project
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(ALLOWED_NAMESPACE_LABELS_ANNOTATION))
+    {
+        Some(allowlist) => allowlist
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .collect(),
+        None => return Ok(()),
+    };
+    let old_labels = old_namespace
+        .and_then(|old| old.metadata.labels.clone())
+        .unwrap_or_default();
+
+    let forbidden: Vec<&str> = namespace
+        .metadata
+        .labels
+        .iter()
+        .flatten()
+        .filter(|(key, value)| {
+            propagated_labels.get(*key) != Some(*value)
+                && old_labels.get(*key) != Some(*value)
+                && !SYSTEM_NAMESPACE_LABELS.contains(&key.as_str())
+                && !allowlist.iter().any(|pattern| glob_match(pattern, key))
+        })
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if forbidden.is_empty() {
+        return Ok(());
+    }
+    Err(PolicyError::Validation(format!(
+        "the Project {project_id} does not allow its Namespaces to have these labels: {}. Allowed labels: {}",
+        forbidden.join(", "),
+        if allowlist.is_empty() {
+            "none, besides the propagated ones".to_string()
+        } else {
+            allowlist.join(", ")
+        }
+    )))
+}
+
+/// Ensure the UPDATE of a Namespace does not change or remove the labels that
+/// have been propagated by its Project. Labels whose old value does not match
+/// the propagated one can be changed
+pub(crate) fn check_immutable_labels(
+    project_id: &ProjectId,
+    namespace: &apicore::Namespace,
+    old_namespace: &apicore::Namespace,
+    propagated_labels: &BTreeMap<String, String>,
+) -> PolicyResult<()> {
+    let labels = namespace.metadata.labels.clone().unwrap_or_default();
+    let old_labels = old_namespace.metadata.labels.clone().unwrap_or_default();
+
+    let changes: Vec<String> = propagated_labels
+        .iter()
+        .filter(|(key, value)| old_labels.get(*key) == Some(*value))
+        .filter_map(|(key, value)| match labels.get(key) {
+            Some(new_value) if new_value == value => None,
+            Some(new_value) => Some(format!("`{key}` changed from `{value}` to `{new_value}`")),
+            None => Some(format!("`{key}` removed")),
+        })
+        .collect();
+    if changes.is_empty() {
+        return Ok(());
+    }
+    Err(PolicyError::Validation(format!(
+        "the labels propagated by the Project {project_id} cannot be changed or removed: {}",
+        changes.join(", ")
+    )))
+}
+
+fn check_label(key: &str, value: &str, settings: &Settings) -> Option<LabelViolation> {
+    if let Err(reason) = validate_label_key(key) {
+        return Some(LabelViolation::InvalidKey {
+            key: key.to_owned(),
+            reason,
+        });
+    }
+    if let Err(reason) = validate_label_value(value) {
+        return Some(LabelViolation::InvalidValue {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            reason,
+        });
+    }
+    if let Some(domain) = reserved_domain(key, settings) {
+        return Some(LabelViolation::Reserved {
+            key: key.to_owned(),
+            domain: domain.to_owned(),
+        });
+    }
+    if PROTECTED_NAMESPACE_LABELS.contains(&key) {
+        return Some(LabelViolation::Collision {
+            key: key.to_owned(),
+        });
+    }
+    if let Some(allowed) = settings.allowed_label_values.get(key) {
+        if !allowed.iter().any(|allowed| allowed == value) {
+            return Some(LabelViolation::NotAllowedValue {
+                key: key.to_owned(),
+                value: value.to_owned(),
+                allowed: allowed.clone(),
+            });
+        }
+    }
+    None
+}
+
+/// Find the reserved domain the prefix of the label key belongs to. The
+/// Pod Security Admission labels are not reserved, unless their domain is
+/// listed inside of the settings
+fn reserved_domain<'a>(key: &str, settings: &'a Settings) -> Option<&'a str> {
+    let (prefix, _) = key.split_once('/')?;
+    let builtin_domains: &[&str] = if USER_SETTABLE_RESERVED_LABELS.contains(&key) {
+        &[]
+    } else {
+        &RESERVED_LABEL_DOMAINS
+    };
+    builtin_domains
+        .iter()
+        .copied()
+        .chain(settings.reserved_label_domains.iter().map(String::as_str))
+        .find(|domain| {
+            prefix == *domain
+                || prefix
+                    .strip_suffix(domain)
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        })
+}
+
+/// Validate a label key: an optional DNS subdomain prefix followed by `/`,
+/// and a name
+pub(crate) fn validate_label_key(key: &str) -> Result<(), String> {
+    let name = match key.split_once('/') {
+        Some((prefix, name)) => {
+            if prefix.is_empty() || !is_dns_subdomain(prefix) {
+                return Err(format!(
+                    "the prefix `{prefix}` is not a valid DNS subdomain"
+                ));
+            }
+            name
+        }
+        None => key,
+    };
+    if name.is_empty() {
+        return Err("the name cannot be empty".to_string());
+    }
+    validate_label_name(name)
+}
+
+/// Validate a label value, which can be empty
+pub(crate) fn validate_label_value(value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Ok(());
+    }
+    validate_label_name(value)
+}
+
+/// At most 63 alphanumeric characters, `-`, `_` or `.`, starting and ending
+/// with an alphanumeric character
+fn validate_label_name(name: &str) -> Result<(), String> {
+    if name.len() > 63 {
+        return Err("must be no more than 63 characters".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err("must consist of alphanumeric characters, `-`, `_` or `.`".to_string());
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        || !name.ends_with(|c: char| c.is_ascii_alphanumeric())
+    {
+        return Err("must start and end with an alphanumeric character".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_outcome;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case::no_allowlist(None, json!({"anything": "x"}), None, Ok(()))]
+    #[case::allowed(
+        Some("team, app.kubernetes.io/*"),
+        json!({"team": "a", "app.kubernetes.io/name": "b", "tier": "gold"}),
+        None,
+        Ok(())
+    )]
+    #[case::system_labels(
+        Some(""),
+        json!({"kubernetes.io/metadata.name": "ns", "field.cattle.io/projectId": "p-abc12"}),
+        None,
+        Ok(())
+    )]
+    #[case::forbidden(
+        Some("team,app.kubernetes.io/*"),
+        json!({"team": "a", "security-posture": "privileged", "env": "dev"}),
+        None,
+        Err("have these labels: env, security-posture. Allowed labels: team, app.kubernetes.io/*")
+    )]
+    #[case::empty_allowlist(
+        Some(""),
+        json!({"env": "dev"}),
+        None,
+        Err("Allowed labels: none, besides the propagated ones")
+    )]
+    #[case::propagated_value_required(
+        Some(""),
+        json!({"tier": "silver"}),
+        None,
+        Err("have these labels: tier.")
+    )]
+    #[case::already_set(Some(""), json!({"env": "dev"}), Some(json!({"env": "dev"})), Ok(()))]
+    #[case::changed(
+        Some(""),
+        json!({"env": "prod"}),
+        Some(json!({"env": "dev"})),
+        Err("have these labels: env.")
+    )]
+    fn namespace_labels(
+        #[case] allowlist: Option<&str>,
+        #[case] labels: serde_json::Value,
+        #[case] old_labels: Option<serde_json::Value>,
+        #[case] expected: Result<(), &str>,
+    ) {
+        let mut project: Project = serde_json::from_value(json!({
+            "metadata": {"name": "p-abc12", "namespace": "local"},
+        }))
+        .expect("cannot deserialize Project");
+        if let Some(allowlist) = allowlist {
+            project.metadata.annotations = Some(BTreeMap::from([(
+                ALLOWED_NAMESPACE_LABELS_ANNOTATION.to_string(),
+                allowlist.to_string(),
+            )]));
+        }
+        let namespace = |labels: serde_json::Value| apicore::Namespace {
+            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
+                labels: Some(serde_json::from_value(labels).expect("cannot deserialize labels")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let old_namespace = old_labels.map(namespace);
+        let propagated = BTreeMap::from([("tier".to_string(), "gold".to_string())]);
+        let project_id: ProjectId = "local:p-abc12".parse().expect("valid project id");
+
+        let result = check_namespace_labels(
+            &project_id,
+            &project,
+            &namespace(labels),
+            old_namespace.as_ref(),
+            &propagated,
+        );
+        assert_outcome(result, expected);
+    }
+
+    #[rstest]
+    #[case::unchanged(json!({"tier": "gold", "env": "dev"}), json!({"tier": "gold"}), Ok(()))]
+    #[case::changed(
+        json!({"tier": "silver"}),
+        json!({"tier": "gold"}),
+        Err("cannot be changed or removed: `tier` changed from `gold` to `silver`")
+    )]
+    #[case::removed(json!({}), json!({"tier": "gold"}), Err("`tier` removed"))]
+    #[case::not_propagated_yet(json!({"tier": "silver"}), json!({"tier": "bronze"}), Ok(()))]
+    fn immutable_labels(
+        #[case] labels: serde_json::Value,
+        #[case] old_labels: serde_json::Value,
+        #[case] expected: Result<(), &str>,
+    ) {
+        let namespace = |labels: serde_json::Value| apicore::Namespace {
+            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
+                labels: Some(serde_json::from_value(labels).expect("cannot deserialize labels")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let propagated = BTreeMap::from([("tier".to_string(), "gold".to_string())]);
+        let project_id: ProjectId = "local:p-abc12".parse().expect("valid project id");
+
+        let result = check_immutable_labels(
+            &project_id,
+            &namespace(labels),
+            &namespace(old_labels),
+            &propagated,
+        );
+        assert_outcome(result, expected);
+    }
+
+    #[rstest]
+    #[case::unchanged_violation(
+        json!({"propagate.team": "a b", "propagate.tier": "gold"}),
+        json!({"propagate.team": "a b"}),
+        Ok(None)
+    )]
+    #[case::new_violation(
+        json!({"propagate.team": "a b", "propagate.env": "x y"}),
+        json!({"propagate.team": "a b"}),
+        Err("`propagate.env`")
+    )]
+    #[case::changed_violation(
+        json!({"propagate.team": "c d"}),
+        json!({"propagate.team": "a b"}),
+        Err("`propagate.team`")
+    )]
+    #[case::fixed(
+        json!({"propagate.team": "payments"}),
+        json!({"propagate.team": "a b"}),
+        Ok(Some(json!({"team": "payments"})))
+    )]
+    fn changed_propagated(
+        #[case] project_labels: serde_json::Value,
+        #[case] old_project_labels: serde_json::Value,
+        #[case] expected: Result<Option<serde_json::Value>, &str>,
+    ) {
+        let project_labels: BTreeMap<String, String> =
+            serde_json::from_value(project_labels).expect("cannot deserialize labels");
+        let old_project_labels: BTreeMap<String, String> =
+            serde_json::from_value(old_project_labels).expect("cannot deserialize labels");
+
+        let result = checked_changed_propagated_labels(
+            "local:p-abc12",
+            &project_labels,
+            &old_project_labels,
+            &Settings::default(),
+        );
+        assert_outcome(
+            result.map(|labels| labels.map(|labels| serde_json::to_value(labels).unwrap())),
+            expected,
+        );
+    }
+
+    #[rstest]
+    #[case("team", true)]
+    #[case("example.com/team", true)]
+    #[case("team.name_1", true)]
+    #[case("", false)]
+    #[case("example.com/", false)]
+    #[case("/team", false)]
+    #[case("Example.com/team", false)]
+    #[case("-team", false)]
+    #[case("team name", false)]
+    #[case("a/b/c", false)]
+    fn label_key(#[case] key: &str, #[case] valid: bool) {
+        assert_eq!(valid, validate_label_key(key).is_ok(), "{key}");
+    }
+
+    #[rstest]
+    #[case("", true)]
+    #[case("strict", true)]
+    #[case("v1.2_3-a", true)]
+    #[case("-strict", false)]
+    #[case("a/b", false)]
+    fn label_value(#[case] value: &str, #[case] valid: bool) {
+        assert_eq!(valid, validate_label_value(value).is_ok(), "{value}");
+    }
+
+    #[rstest]
+    #[case::valid(
+        json!({"propagate.team": "payments", "propagate.acme.org/tier": "gold", "other": "x"}),
+        Ok(json!({"team": "payments", "acme.org/tier": "gold"}))
+    )]
+    #[case::invalid_key(json!({"propagate.-team": "payments"}), Err(vec!["invalid label key"]))]
+    #[case::invalid_value(json!({"propagate.team": "a b"}), Err(vec!["invalid label value `a b`"]))]
+    #[case::reserved(
+        json!({"propagate.kubernetes.io/metadata.name": "x"}),
+        Err(vec!["the `kubernetes.io` label domain is reserved"])
+    )]
+    #[case::reserved_subdomain(
+        json!({"propagate.node.k8s.io/role": "x"}),
+        Err(vec!["the `k8s.io` label domain is reserved"])
+    )]
+    #[case::pod_security(
+        json!({"propagate.pod-security.kubernetes.io/enforce": "restricted"}),
+        Ok(json!({"pod-security.kubernetes.io/enforce": "restricted"}))
+    )]
+    #[case::pod_security_unknown_key(
+        json!({"propagate.pod-security.kubernetes.io/exempt": "true"}),
+        Err(vec!["the `kubernetes.io` label domain is reserved"])
+    )]
+    #[case::reserved_by_settings(
+        json!({"propagate.policy.example.com/exempt": "true"}),
+        Err(vec!["the `example.com` label domain is reserved"])
+    )]
+    #[case::not_reserved_lookalike(
+        json!({"propagate.notk8s.io/role": "x"}),
+        Ok(json!({"notk8s.io/role": "x"}))
+    )]
+    #[case::collision(
+        json!({"propagate.field.cattle.io/projectId": "p-abc12"}),
+        Err(vec!["would overwrite the `field.cattle.io/projectId` label"])
+    )]
+    #[case::policy_label_collision(
+        json!({"propagate.project.kubewarden.io/name": "payments"}),
+        Err(vec!["would overwrite the `project.kubewarden.io/name` label managed by Rancher or by the policy"])
+    )]
+    #[case::not_allowed_value(
+        json!({"propagate.tier": "platinum"}),
+        Err(vec!["value `platinum` is not allowed, must be one of: gold, silver"])
+    )]
+    #[case::all_violations_reported(
+        json!({"propagate.tier": "platinum", "propagate.team": "a b"}),
+        Err(vec!["`propagate.tier`", "`propagate.team`"])
+    )]
+    fn propagated(
+        #[case] project_labels: serde_json::Value,
+        #[case] expected: Result<serde_json::Value, Vec<&str>>,
+    ) {
+        let settings: Settings = serde_json::from_value(json!({
+            "reserved_label_domains": ["example.com"],
+            "allowed_label_values": {"tier": ["gold", "silver"]},
+        }))
+        .expect("cannot deserialize settings");
+        let project_labels: BTreeMap<String, String> =
+            serde_json::from_value(project_labels).expect("cannot deserialize labels");
+
+        let result = checked_propagated_labels("local:p-abc12", &project_labels, &settings);
+        assert_outcome(
+            result.map(|labels| serde_json::to_value(labels).unwrap()),
+            expected,
+        );
+    }
+}
@@ -1,31 +1,75 @@
 use guest::prelude::*;
 use kubewarden_policy_sdk::wapc_guest as guest;
 
-use anyhow::{anyhow, Result};
 use k8s_openapi::api::core::v1 as apicore;
 use lazy_static::lazy_static;
-use slog::{o, warn, Logger};
+use slog::{info, o, warn, Logger};
 use std::collections::BTreeMap;
 
 extern crate kubewarden_policy_sdk as kubewarden;
 use kubewarden::{
     host_capabilities::kubernetes::GetResourceRequest, logging, protocol_version_guest,
-    request::ValidationRequest, validate_settings,
+    request::ValidationRequest, response::ValidationResponse, validate_settings,
 };
 
 #[cfg(test)]
 use crate::tests::mock_kubernetes_sdk::get_resource;
+#[cfg(test)]
+use crate::tests::mock_kubernetes_sdk::list_all_resources;
+#[cfg(test)]
+use crate::tests::mock_kubernetes_sdk::list_resources_by_namespace;
 #[cfg(not(test))]
 use kubewarden::host_capabilities::kubernetes::get_resource;
+#[cfg(not(test))]
+use kubewarden::host_capabilities::kubernetes::list_all_resources;
+#[cfg(not(test))]
+use kubewarden::host_capabilities::kubernetes::list_resources_by_namespace;
 
 mod custom_resources;
 use custom_resources::Project;
 
+mod downstream;
+
+mod drift;
+
+mod glob;
+
+mod labels;
+
+mod naming;
+
+mod errors;
+use errors::{Lookup, PolicyError, PolicyResult};
+
+mod project_id;
+use project_id::ProjectId;
+
+mod quota;
+
+mod project_quota;
+
+mod project_name;
+
+mod project_namespaces;
+
+mod readiness;
+
+mod resource_defaults;
+
+mod selector;
+
 mod settings;
-use settings::Settings;
+use settings::{EnforcementMode, Settings};
+
+#[cfg(test)]
+mod test_utils;
 
 const RANCHER_PROJECT_ID_LABEL: &str = "field.cattle.io/projectId";
 
+/// Project annotation that allows the deletion of a Project that still has
+/// Namespaces, when set to `true`
+const ALLOW_DELETION_ANNOTATION: &str = "project.kubewarden.io/allow-deletion";
+
 lazy_static! {
     static ref LOG_DRAIN: Logger = Logger::root(
         logging::KubewardenDrain::new(),
@@ -43,126 +87,659 @@ pub extern "C" fn wapc_init() {
 fn validate(payload: &[u8]) -> CallResult {
     let validation_request: ValidationRequest<Settings> = ValidationRequest::new(payload)?;
 
-    let namespace =
-        serde_json::from_value::<apicore::Namespace>(validation_request.request.object)?;
+    if let Some(reason) = exemption_reason(&validation_request) {
+        info!(LOG_DRAIN, "request exempted"; "reason" => reason);
+        return Verdict::default().into_response();
+    }
+
+    let result = match validation_request.request.kind.kind.as_str() {
+        "Project" => validate_project(&validation_request),
+        _ => validate_namespace(&validation_request),
+    };
+    match result {
+        Ok(verdict) => verdict.into_response(),
+        Err(err) => err.reject(),
+    }
+}
+
+/// Explain why the request is exempted from the policy. Namespace exemptions
+/// apply only to Namespace requests
+fn exemption_reason(validation_request: &ValidationRequest<Settings>) -> Option<String> {
+    let request = &validation_request.request;
+    let namespace = match request.kind.kind.as_str() {
+        "Project" => None,
+        _ if !request.name.is_empty() => Some(request.name.as_str()),
+        _ => request
+            .object
+            .pointer("/metadata/name")
+            .and_then(serde_json::Value::as_str),
+    };
+    validation_request
+        .settings
+        .exemptions
+        .reason(&request.user_info, namespace)
+}
+
+/// The outcome of the evaluation of a request that has been accepted
+#[derive(Debug, Default)]
+struct Verdict {
+    /// The patched Namespace, set only when a mutation is required
+    patched_namespace: Option<apicore::Namespace>,
+    /// Warnings returned to the user
+    warnings: Vec<String>,
+}
+
+impl Verdict {
+    fn into_response(self) -> CallResult {
+        let mutated_object = self
+            .patched_namespace
+            .map(serde_json::to_value)
+            .transpose()?;
+        Ok(serde_json::to_vec(&ValidationResponse {
+            accepted: true,
+            message: None,
+            code: None,
+            mutated_object,
+            audit_annotations: None,
+            warnings: (!self.warnings.is_empty()).then_some(self.warnings),
+        })?)
+    }
+
+    /// Reject the request or just warn the user, depending on the enforcement mode
+    fn enforce(&mut self, mode: EnforcementMode, msg: String) -> PolicyResult<()> {
+        match mode {
+            EnforcementMode::Reject => Err(PolicyError::Validation(msg)),
+            EnforcementMode::Warn => {
+                warn!(LOG_DRAIN, "{}", msg);
+                self.warnings.push(msg);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Evaluate the Project contained inside of the request, ensuring all its
+/// labels can be propagated to its Namespaces.
+///
+/// On UPDATE only what is changed by the request is checked, so that the
+/// Projects that were already invalid before the policy was deployed can
+/// still be updated by Rancher. Projects being deleted are not checked
+fn validate_project(validation_request: &ValidationRequest<Settings>) -> PolicyResult<Verdict> {
+    if validation_request.request.operation == "DELETE" {
+        return validate_project_deletion(validation_request);
+    }
+
+    let project = serde_json::from_value::<Project>(validation_request.request.object.clone())
+        .map_err(|e| PolicyError::Deserialization {
+            what: "Project".to_string(),
+            details: e.to_string(),
+        })?;
+    let old_project = if validation_request.request.old_object.is_null() {
+        None
+    } else {
+        Some(
+            serde_json::from_value::<Project>(validation_request.request.old_object.clone())
+                .map_err(|e| PolicyError::Deserialization {
+                    what: "the old Project".to_string(),
+                    details: e.to_string(),
+                })?,
+        )
+    };
+    let settings = &validation_request.settings;
+    let mut verdict = Verdict::default();
+
+    if project.metadata.deletion_timestamp.is_some() {
+        return Ok(verdict);
+    }
+
+    let project_name = format!(
+        "{}:{}",
+        project.metadata.namespace.as_deref().unwrap_or_default(),
+        project.metadata.name.as_deref().unwrap_or_default()
+    );
+    let project_labels = project.metadata.labels.clone().unwrap_or_default();
+    let propagated_labels = match &old_project {
+        Some(old_project) => labels::checked_changed_propagated_labels(
+            &project_name,
+            &project_labels,
+            old_project
+                .metadata
+                .labels
+                .as_ref()
+                .unwrap_or(&BTreeMap::new()),
+            settings,
+        )?,
+        None => Some(labels::checked_propagated_labels(
+            &project_name,
+            &project_labels,
+            settings,
+        )?),
+    };
+    if let Some(spec) = &project.spec {
+        if old_project.as_ref().is_none_or(|old_project| {
+            project_quota::quotas_changed(Some(spec), old_project.spec.as_ref())
+        }) {
+            project_quota::check_project_quotas(&project_name, spec)?;
+        }
+    }
+    if old_project
+        .as_ref()
+        .is_none_or(|old_project| naming::NamingRule::changed(&project, old_project))
+    {
+        naming::NamingRule::from_project(&project)?;
+    }
+
+    // the drift cannot be analyzed when the Project has labels that cannot
+    // be propagated: the updates of its Namespaces are rejected anyway
+    if let (Some(old_project), Some(propagated_labels)) = (&old_project, &propagated_labels) {
+        if settings.analyze_project_label_changes {
+            check_project_label_drift(
+                &project,
+                old_project,
+                propagated_labels,
+                settings,
+                &mut verdict,
+            )?;
+        }
+    }
+
+    Ok(verdict)
+}
+
+/// Look for the Namespaces that would keep outdated labels once the labels
+/// propagated by the Project are changed. Only the Projects of the local
+/// cluster can be checked, the Namespaces of the downstream clusters cannot be
+/// listed
+fn check_project_label_drift(
+    project: &Project,
+    old_project: &Project,
+    propagated_labels: &BTreeMap<String, String>,
+    settings: &Settings,
+    verdict: &mut Verdict,
+) -> PolicyResult<()> {
+    // the labels propagated by the old Project could be invalid, in that case
+    // none of them has been propagated
+    let previous_labels = labels::propagated_labels(
+        old_project
+            .metadata
+            .labels
+            .as_ref()
+            .unwrap_or(&BTreeMap::new()),
+        settings,
+    )
+    .unwrap_or_default();
+    if &previous_labels == propagated_labels {
+        return Ok(());
+    }
+
+    let project_id = match local_project_id(project, settings) {
+        Some(project_id) => project_id,
+        None => return Ok(()),
+    };
+
+    let drifting = drift::drifting_namespaces(
+        &project_id,
+        propagated_labels,
+        &previous_labels,
+        &settings.local_cluster_id,
+    )?;
+    if drifting.is_empty() {
+        return Ok(());
+    }
+
+    verdict.enforce(
+        settings.project_label_drift_mode,
+        format!(
+            "the labels of {} Namespaces of the Project {project_id} will not match the ones propagated by the Project until they are updated: {}",
+            drifting.len(),
+            project_namespaces::describe_namespaces(&drifting)
+        ),
+    )
+}
 
-    let cluster_project_tuple: Option<(String, String)> = namespace
+/// Reject the deletion of a Project that still has Namespaces, unless the
+/// Project has the `project.kubewarden.io/allow-deletion` annotation.
+/// Only the Projects of the local cluster can be checked
+fn validate_project_deletion(
+    validation_request: &ValidationRequest<Settings>,
+) -> PolicyResult<Verdict> {
+    let project = serde_json::from_value::<Project>(validation_request.request.old_object.clone())
+        .map_err(|e| PolicyError::Deserialization {
+            what: "the deleted Project".to_string(),
+            details: e.to_string(),
+        })?;
+    let settings = &validation_request.settings;
+
+    let deletion_allowed = project
         .metadata
         .annotations
         .as_ref()
-        .and_then(|annotations| annotations.get(RANCHER_PROJECT_ID_LABEL).cloned())
-        .map(|annotation| {
-            let tokens: Vec<&str> = annotation.split(':').collect();
-            if tokens.len() != 2 {
-                Err(anyhow!(
-                    "malformed value of {} annotation",
-                    RANCHER_PROJECT_ID_LABEL
-                ))
-            } else {
-                Ok((tokens[0].to_owned(), tokens[1].to_owned()))
-            }
-        })
-        .transpose()?;
+        .and_then(|annotations| annotations.get(ALLOW_DELETION_ANNOTATION))
+        .is_some_and(|value| value == "true");
+    if deletion_allowed {
+        return Ok(Verdict::default());
+    }
+    let project_id = match local_project_id(&project, settings) {
+        Some(project_id) => project_id,
+        None => return Ok(Verdict::default()),
+    };
+
+    let names: Vec<String> =
+        project_namespaces::list_project_namespaces(&project_id, &settings.local_cluster_id)?
+            .into_iter()
+            .filter_map(|namespace| namespace.metadata.name)
+            .collect();
+    if names.is_empty() {
+        return Ok(Verdict::default());
+    }
+    Err(PolicyError::Validation(format!(
+        "the Project {project_id} cannot be deleted, {} Namespaces still belong to it: {}. \
+        Move them to another Project, or set the `{ALLOW_DELETION_ANNOTATION}: \"true\"` annotation on the Project",
+        names.len(),
+        project_namespaces::describe_namespaces(&names)
+    )))
+}
+
+/// The id of a Project of the local cluster, `None` when the Project belongs
+/// to a downstream cluster
+fn local_project_id(project: &Project, settings: &Settings) -> Option<ProjectId> {
+    let cluster_id = project
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.cluster_name.clone())
+        .or_else(|| project.metadata.namespace.clone())?;
+    if cluster_id != settings.local_cluster_id {
+        return None;
+    }
+    Some(ProjectId {
+        cluster_id,
+        project: project.metadata.name.clone()?,
+    })
+}
+
+/// Evaluate the Namespace contained inside of the request
+fn validate_namespace(validation_request: &ValidationRequest<Settings>) -> PolicyResult<Verdict> {
+    let namespace =
+        serde_json::from_value::<apicore::Namespace>(validation_request.request.object.clone())
+            .map_err(|e| PolicyError::Deserialization {
+                what: "Namespace".to_string(),
+                details: e.to_string(),
+            })?;
+
+    let old_namespace = if validation_request.request.old_object.is_null() {
+        None
+    } else {
+        Some(
+            serde_json::from_value::<apicore::Namespace>(
+                validation_request.request.old_object.clone(),
+            )
+            .map_err(|e| PolicyError::Deserialization {
+                what: "the old Namespace".to_string(),
+                details: e.to_string(),
+            })?,
+        )
+    };
+
+    let settings = &validation_request.settings;
+    let mut verdict = Verdict::default();
+
+    if let Some(selector) = &settings.namespace_selector {
+        let labels = namespace.metadata.labels.clone().unwrap_or_default();
+        if !selector::selector_matches(selector, &labels) {
+            info!(
+                LOG_DRAIN,
+                "Namespace not matched by the namespace_selector, skipping it";
+                "namespace" => namespace.metadata.name.as_deref().unwrap_or("NOT SET"),
+            );
+            return Ok(verdict);
+        }
+    }
+
+    let mut patched_namespace = namespace.clone();
+    let project_id =
+        match ProjectId::from_metadata(&namespace.metadata, &settings.local_cluster_id)? {
+            Some(project_id) => project_id,
+            None => match named_project_assignment(&namespace, settings)?
+                .or_else(|| default_project_assignment(validation_request, &namespace))
+            {
+                Some(project_id) => {
+                    assign_project(&project_id, &mut patched_namespace);
+                    project_id
+                }
+                None => return Ok(verdict),
+            },
+        };
+    let project = match resolve_project(&project_id, &namespace, settings)? {
+        Some(project) => project,
+        None => return Ok(verdict),
+    };
+
+    check_project_cluster(&project_id, &project, settings, &mut verdict)?;
 
-    match cluster_project_tuple {
-        None => kubewarden::accept_request(),
-        Some((cluster_id, project_id)) => propagate_labels(
-            &cluster_id,
+    let old_project_id = old_namespace.as_ref().and_then(|old| {
+        ProjectId::from_metadata(&old.metadata, &settings.local_cluster_id)
+            .ok()
+            .flatten()
+    });
+    let joining = old_project_id.as_ref() != Some(&project_id);
+    if joining {
+        check_project_readiness(&project_id, &project, settings, &mut verdict)?;
+        naming::check_namespace_name(&project_id, &project, &namespace)?;
+        project_namespaces::check_namespaces_limit(
             &project_id,
+            &project,
             &namespace,
-            &validation_request.settings,
-        ),
+            settings.max_namespaces_per_project,
+            &settings.local_cluster_id,
+        )?;
+    }
+
+    let propagated_labels = propagate_labels(
+        &project_id,
+        &project,
+        &mut patched_namespace,
+        joining,
+        settings,
+        &mut verdict,
+    )?;
+    labels::check_namespace_labels(
+        &project_id,
+        &project,
+        &patched_namespace,
+        old_namespace.as_ref(),
+        &propagated_labels,
+    )?;
+    if let Some(old_namespace) = old_namespace.as_ref() {
+        if settings.immutable_propagated_labels
+            && old_project_id.as_ref() == Some(&project_id)
+            && !settings
+                .immutable_propagated_labels_exemptions
+                .contains(&validation_request.request.user_info)
+        {
+            labels::check_immutable_labels(
+                &project_id,
+                &namespace,
+                old_namespace,
+                &propagated_labels,
+            )?;
+        }
+    }
+    if settings.inject_namespace_resource_quota {
+        resource_defaults::inject_namespace_resource_quota(&project, &mut patched_namespace)?;
     }
+    if settings.inject_container_default_resource_limit {
+        resource_defaults::inject_container_default_resource_limit(
+            &project,
+            &mut patched_namespace,
+            settings.overwrite_container_default_resource_limit,
+        )?;
+    }
+
+    if settings.enforce_project_resource_quota {
+        project_quota::check_namespace_quota(
+            &project_id,
+            &project,
+            &patched_namespace,
+            old_namespace.as_ref(),
+            old_project_id.as_ref(),
+        )?;
+    }
+
+    if patched_namespace != namespace {
+        verdict.patched_namespace = Some(patched_namespace);
+    }
+    Ok(verdict)
 }
 
-fn propagate_labels(
-    cluster_id: &str,
-    project_id: &str,
+/// The Project referenced by the `project.kubewarden.io/name` label of a
+/// Namespace that does not belong to any Project yet
+fn named_project_assignment(
     namespace: &apicore::Namespace,
     settings: &Settings,
-) -> CallResult {
-    if cluster_id != "local" {
-        let msg = "Namespace belongs to project defined inside of an upstream cluster. This is not supported yet, accepting the request";
+) -> PolicyResult<Option<ProjectId>> {
+    let display_name = match namespace
+        .metadata
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.get(project_name::PROJECT_NAME_LABEL))
+    {
+        Some(display_name) => display_name,
+        None => return Ok(None),
+    };
+    let project_id = project_name::resolve_project_name(
+        display_name,
+        settings.projects_namespace(),
+        &settings.local_cluster_id,
+    )?;
+    info!(
+        LOG_DRAIN,
+        "assigning the Namespace to the Project referenced by its display name";
+        "namespace" => namespace.metadata.name.as_deref().unwrap_or("NOT SET"),
+        "display_name" => display_name,
+        "project_id" => project_id.to_string(),
+    );
+    Ok(Some(project_id))
+}
 
-        let namespace_name = namespace
-            .metadata
-            .name
-            .as_ref()
-            .cloned()
-            .unwrap_or_else(|| String::from("NOT SET"));
-        warn!(
-            LOG_DRAIN,
-            "{}", msg;
-            "namespace" => namespace_name,
-            "project_id" => project_id,
-            "cluster_id" => cluster_id,
+/// The default Project a Namespace created without a Project must be assigned to
+fn default_project_assignment(
+    validation_request: &ValidationRequest<Settings>,
+    namespace: &apicore::Namespace,
+) -> Option<ProjectId> {
+    if validation_request.request.operation != "CREATE" {
+        return None;
+    }
+    let name = namespace.metadata.name.as_deref().unwrap_or_default();
+    let project_id = validation_request.settings.default_project_for(name)?;
+    info!(
+        LOG_DRAIN,
+        "assigning the Namespace to the default Project";
+        "namespace" => name,
+        "project_id" => project_id.to_string(),
+    );
+    Some(project_id)
+}
+
+/// Add the Namespace to the Project, setting the `field.cattle.io/projectId`
+/// annotation and label like Rancher does
+fn assign_project(project_id: &ProjectId, namespace: &mut apicore::Namespace) {
+    namespace
+        .metadata
+        .annotations
+        .get_or_insert_with(BTreeMap::new)
+        .insert(RANCHER_PROJECT_ID_LABEL.to_string(), project_id.to_string());
+    namespace
+        .metadata
+        .labels
+        .get_or_insert_with(BTreeMap::new)
+        .insert(
+            RANCHER_PROJECT_ID_LABEL.to_string(),
+            project_id.project.clone(),
         );
+}
 
-        return match settings.downstream_cluster_failure_mode {
-            settings::FailureMode::Fail => {
-                kubewarden::reject_request(Some(msg.to_string()), None, None, None)
+/// Find the Project the Namespace belongs to. Returns `None` when the Project
+/// is defined inside of a downstream cluster that cannot be inspected, and
+/// the failure mode allows the request
+fn resolve_project(
+    project_id: &ProjectId,
+    namespace: &apicore::Namespace,
+    settings: &Settings,
+) -> PolicyResult<Option<Project>> {
+    if project_id.cluster_id == settings.local_cluster_id {
+        return fetch_project(&project_id.project, settings.projects_namespace()).map(Some);
+    }
+
+    fetch_downstream_project(project_id, settings)
+        .map(Some)
+        .or_else(|err| {
+            let namespace_name = namespace
+                .metadata
+                .name
+                .as_ref()
+                .cloned()
+                .unwrap_or_else(|| String::from("NOT SET"));
+            warn!(
+                LOG_DRAIN,
+                "cannot find the Project of a downstream cluster";
+                "namespace" => namespace_name,
+                "project_id" => &project_id.project,
+                "cluster_id" => &project_id.cluster_id,
+                "error" => err.to_string(),
+            );
+
+            let (failure_mode, rule) = settings.downstream_failure_mode(&project_id.cluster_id);
+            info!(
+                LOG_DRAIN,
+                "downstream cluster failure mode selected";
+                "cluster_id" => &project_id.cluster_id,
+                "rule" => rule,
+                "failure_mode" => format!("{failure_mode:?}"),
+            );
+
+            match failure_mode {
+                settings::FailureMode::Fail => Err(err),
+                settings::FailureMode::Ignore => Ok(None),
             }
-            settings::FailureMode::Ignore => kubewarden::accept_request(),
+        })
+}
+
+/// Ensure the Project belongs to the cluster referenced by the Namespace.
+/// This protects against annotations copied from a Namespace of another
+/// cluster, that would pull the labels of a same-named Project
+fn check_project_cluster(
+    project_id: &ProjectId,
+    project: &Project,
+    settings: &Settings,
+    verdict: &mut Verdict,
+) -> PolicyResult<()> {
+    let cluster_name = match project
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.cluster_name.as_deref())
+    {
+        Some(cluster_name) => cluster_name,
+        None => return Ok(()),
+    };
+    if cluster_name == project_id.cluster_id {
+        return Ok(());
+    }
+
+    verdict.enforce(
+        settings.project_cluster_mismatch_mode,
+        format!(
+            "the Project {project_id} belongs to the `{cluster_name}` cluster, not to the `{}` cluster referenced by the Namespace",
+            project_id.cluster_id
+        ),
+    )
+}
+
+/// Ensure the Namespace is not joining a Project that is still being
+/// initialized by Rancher, or that is being deleted. Namespaces that already
+/// belonged to the Project are not checked
+fn check_project_readiness(
+    project_id: &ProjectId,
+    project: &Project,
+    settings: &Settings,
+    verdict: &mut Verdict,
+) -> PolicyResult<()> {
+    match readiness::not_ready_reason(project) {
+        Some(reason) => verdict.enforce(
+            settings.project_not_ready_mode,
+            format!("the Project {project_id} {reason}"),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Copy the labels propagated by the Project into the Namespace. Returns the
+/// propagated labels.
+///
+/// A Namespace cannot join a Project that has labels that cannot be
+/// propagated. The Namespaces that already belong to the Project receive the
+/// valid labels only, and a warning: rejecting them would block all their
+/// updates, including the ones done by Rancher
+fn propagate_labels(
+    project_id: &ProjectId,
+    project: &Project,
+    namespace: &mut apicore::Namespace,
+    joining: bool,
+    settings: &Settings,
+    verdict: &mut Verdict,
+) -> PolicyResult<BTreeMap<String, String>> {
+    let (propagated_labels, violations) = labels::partitioned_propagated_labels(
+        project.metadata.labels.as_ref().unwrap_or(&BTreeMap::new()),
+        settings,
+    );
+    if !violations.is_empty() {
+        let mode = if joining {
+            EnforcementMode::Reject
+        } else {
+            EnforcementMode::Warn
         };
+        verdict.enforce(
+            mode,
+            labels::violations_message(&project_id.to_string(), &violations),
+        )?;
+    }
+    if let Some(new_labels) = merge_labels(&propagated_labels, namespace.metadata.labels.as_ref()) {
+        namespace.metadata.labels = Some(new_labels);
+    }
+    Ok(propagated_labels)
+}
+
+/// Get the Project of a downstream cluster from the mirror defined inside of
+/// the settings
+fn fetch_downstream_project(project_id: &ProjectId, settings: &Settings) -> PolicyResult<Project> {
+    match &settings.downstream_projects_mirror {
+        Some(mirror) => downstream::fetch_mirrored_project(mirror, project_id),
+        None => Err(PolicyError::Validation(
+            "Namespace belongs to project defined inside of a downstream cluster and no downstream_projects_mirror is configured".to_string(),
+        )),
     }
+}
 
+/// Get the Project with the given name, defined inside of the given Namespace
+fn fetch_project(name: &str, namespace: &str) -> PolicyResult<Project> {
     let req = GetResourceRequest {
         api_version: "management.cattle.io/v3".to_string(),
         kind: "Project".to_string(),
-        name: project_id.to_string(),
-        namespace: Some(cluster_id.to_string()),
+        name: name.to_string(),
+        namespace: Some(namespace.to_string()),
         disable_cache: true,
     };
-    let project: Project = get_resource(&req)?;
-
-    match merge_labels(
-        &project.metadata.labels.unwrap_or_default(),
-        namespace.metadata.labels.as_ref(),
-    )? {
-        Some(new_labels) => {
-            let mut patched_namespace = namespace.clone();
-            patched_namespace.metadata.labels = Some(new_labels);
-            kubewarden::mutate_request(serde_json::to_value(patched_namespace)?)
-        }
-        None => kubewarden::accept_request(),
-    }
+    get_resource(&req)
+        .map_err(|e| PolicyError::from_lookup(Lookup::get_project(namespace, name), e))
 }
 
+/// Merge the labels propagated by the Project into the labels of the
+/// Namespace. Returns `None` when the Namespace labels are not changed
 fn merge_labels(
-    project_labels: &BTreeMap<String, String>,
+    propagated_labels: &BTreeMap<String, String>,
     namespace_labels: Option<&BTreeMap<String, String>>,
-) -> Result<Option<BTreeMap<String, String>>> {
+) -> Option<BTreeMap<String, String>> {
     let mut labels_changed = false;
     let mut namespace_labels = match namespace_labels {
         Some(labels) => labels.to_owned(),
         None => BTreeMap::<String, String>::new(),
     };
 
-    for (key, value) in project_labels.iter() {
-        if key.starts_with("propagate.") {
-            let patched_key = key
-                .strip_prefix("propagate.")
-                .ok_or_else(|| anyhow!("strip prefix should always return something"))?;
-            namespace_labels
-                .entry(patched_key.to_owned())
-                .and_modify(|v| {
-                    if v != value {
-                        value.clone_into(v);
-                        labels_changed = true;
-                    }
-                })
-                .or_insert_with(|| {
+    for (key, value) in propagated_labels.iter() {
+        namespace_labels
+            .entry(key.to_owned())
+            .and_modify(|v| {
+                if v != value {
+                    value.clone_into(v);
                     labels_changed = true;
-                    value.to_owned()
-                });
-        }
+                }
+            })
+            .or_insert_with(|| {
+                labels_changed = true;
+                value.to_owned()
+            });
     }
 
-    if labels_changed {
-        Ok(Some(namespace_labels))
-    } else {
-        Ok(None)
-    }
+    labels_changed.then_some(namespace_labels)
 }
 
 #[cfg(test)]
@@ -170,7 +747,9 @@ mod tests {
     use crate::settings::FailureMode;
 
     use super::*;
-    use kubewarden::{request::KubernetesAdmissionRequest, response::ValidationResponse};
+    use anyhow::anyhow;
+    use k8s_openapi::DeepMerge;
+    use kubewarden::request::KubernetesAdmissionRequest;
     use mockall::automock;
     use rstest::*;
     use serde_json::json;
@@ -179,7 +758,7 @@ mod tests {
     #[automock]
     pub mod kubernetes_sdk {
         use kubewarden::host_capabilities::kubernetes::{
-            GetResourceRequest, ListResourcesByNamespaceRequest,
+            GetResourceRequest, ListAllResourcesRequest, ListResourcesByNamespaceRequest,
         };
 
         #[allow(dead_code)]
@@ -196,6 +775,57 @@ mod tests {
         {
             Err(anyhow::anyhow!("not mocked"))
         }
+
+        #[allow(dead_code)]
+        pub fn list_all_resources<T>(
+            _req: &ListAllResourcesRequest,
+        ) -> anyhow::Result<k8s_openapi::List<T>>
+        where
+            T: k8s_openapi::ListableResource + serde::de::DeserializeOwned + Clone + 'static,
+        {
+            Err(anyhow::anyhow!("not mocked"))
+        }
+    }
+
+    /// Build a Namespace with the given labels. When `project_id` is set, the
+    /// Namespace belongs to the Project through the `field.cattle.io/projectId`
+    /// annotation
+    fn namespace_in_project(
+        name: &str,
+        project_id: Option<&str>,
+        labels: serde_json::Value,
+    ) -> apicore::Namespace {
+        apicore::Namespace {
+            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
+                name: Some(name.to_string()),
+                annotations: project_id.map(|project_id| {
+                    BTreeMap::from([(RANCHER_PROJECT_ID_LABEL.to_string(), project_id.to_string())])
+                }),
+                labels: Some(serde_json::from_value(labels).expect("cannot deserialize labels")),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// The kind of the requests that evaluate a Project
+    fn project_kind() -> kubewarden::request::GroupVersionKind {
+        kubewarden::request::GroupVersionKind {
+            group: "management.cattle.io".to_string(),
+            version: "v3".to_string(),
+            kind: "Project".to_string(),
+        }
+    }
+
+    /// Evaluate the request with the given settings
+    fn run(settings: Settings, request: KubernetesAdmissionRequest) -> ValidationResponse {
+        let validation_request = ValidationRequest::<Settings> { settings, request };
+        let payload = serde_json::to_string(&validation_request)
+            .expect("cannot serialize validation request");
+
+        let response = validate(payload.as_bytes());
+        assert!(response.is_ok());
+        serde_json::from_slice(&response.unwrap()).expect("cannot deserialize validation_response")
     }
 
     #[rstest]
@@ -263,8 +893,9 @@ mod tests {
             serde_json::from_value(labels).expect("cannot deserialize expected labels")
         });
 
-        let actual = merge_labels(&project_labels, namespace_labels.as_ref())
-            .expect("merge should not fail");
+        let propagated_labels = labels::propagated_labels(&project_labels, &Settings::default())
+            .expect("project labels should be valid");
+        let actual = merge_labels(&propagated_labels, namespace_labels.as_ref());
 
         assert_eq!(expected_labels, actual);
     }
@@ -333,7 +964,7 @@ mod tests {
         #[case] ns_labels: serde_json::Value,
         #[case] should_mutate: bool,
     ) {
-        let prj_name = "test-namespace".to_string();
+        let prj_name = "p-abc12".to_string();
         let cluster_id = "local";
 
         let project_labels: BTreeMap<String, String> =
@@ -348,34 +979,17 @@ mod tests {
             ..Default::default()
         };
 
-        let namespace_labels: BTreeMap<String, String> =
-            serde_json::from_value(ns_labels).expect("cannot deserialize pod labels");
-
-        let mut namespace_annotations: BTreeMap<String, String> = BTreeMap::new();
-        namespace_annotations.insert(
-            RANCHER_PROJECT_ID_LABEL.to_string(),
-            format!("{cluster_id}:{prj_name}"),
+        let namespace = namespace_in_project(
+            "testing-namespace",
+            Some(&format!("{cluster_id}:{prj_name}")),
+            ns_labels,
         );
 
-        let namespace = apicore::Namespace {
-            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
-                name: Some("testing-namespace".to_string()),
-                labels: Some(namespace_labels),
-                annotations: Some(namespace_annotations),
-                ..Default::default()
-            },
-            ..Default::default()
-        };
-
         let settings = Settings::default();
         let request = KubernetesAdmissionRequest {
             object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
             ..Default::default()
         };
-        let validation_request = ValidationRequest::<Settings> { settings, request };
-        let payload = serde_json::to_string(&validation_request)
-            .expect("cannot serialize validation request");
-
         let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
         ctx_get_resource
             .expect::<Project>()
@@ -388,10 +1002,7 @@ mod tests {
                 }
             });
 
-        let response = validate(payload.as_bytes());
-        assert!(response.is_ok());
-        let validation_response: ValidationResponse = serde_json::from_slice(&response.unwrap())
-            .expect("cannot deserialize validation_response");
+        let validation_response = run(settings, request);
 
         assert!(validation_response.accepted);
         if should_mutate && validation_response.mutated_object.is_none() {
@@ -403,47 +1014,1023 @@ mod tests {
     }
 
     #[rstest]
-    #[case(FailureMode::Ignore, true)]
-    #[case(FailureMode::Fail, false)]
+    #[case(
+        "projects.management.cattle.io \"p-abc12\" not found",
+        404,
+        "cannot find projects.management.cattle.io `p-abc12`"
+    )]
+    #[case(
+        "projects.management.cattle.io \"p-abc12\" is forbidden",
+        500,
+        "read-rancher-projects-local"
+    )]
     #[serial]
-    fn downstream_cluster(#[case] failure_mode: FailureMode, #[case] accepted: bool) {
-        let prj_name = "test-namespace".to_string();
-        let cluster_id = "downstream-cluster-1";
-
-        let mut namespace_annotations: BTreeMap<String, String> = BTreeMap::new();
-        namespace_annotations.insert(
-            RANCHER_PROJECT_ID_LABEL.to_string(),
-            format!("{cluster_id}:{prj_name}"),
-        );
+    fn project_lookup_failure(
+        #[case] lookup_error: &'static str,
+        #[case] expected_code: u16,
+        #[case] expected_message: &str,
+    ) {
+        let namespace = namespace_in_project("testing-namespace", Some("local:p-abc12"), json!({}));
 
-        let namespace = apicore::Namespace {
-            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
-                name: Some("testing-namespace".to_string()),
-                annotations: Some(namespace_annotations),
-                ..Default::default()
-            },
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
             ..Default::default()
         };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(1)
+            .returning(move |_| Err(anyhow!(lookup_error)));
+
+        let validation_response = run(Settings::default(), request);
+
+        assert!(!validation_response.accepted);
+        assert_eq!(Some(expected_code), validation_response.code);
+        let message = validation_response.message.expect("message should be set");
+        assert!(message.contains(expected_message), "{message}");
+    }
+
+    #[rstest]
+    #[case("local", None, "local:p-abc12", Some("local"))]
+    #[case("c-m-abcd1234", None, "c-m-abcd1234:p-abc12", Some("c-m-abcd1234"))]
+    #[case("c-m-abcd1234", Some("local"), "c-m-abcd1234:p-abc12", Some("local"))]
+    #[case("c-m-abcd1234", None, "local:p-abc12", None)]
+    #[serial]
+    fn custom_local_cluster(
+        #[case] local_cluster_id: &str,
+        #[case] local_cluster_projects_namespace: Option<&str>,
+        #[case] annotation: &str,
+        #[case] expected_lookup_namespace: Option<&'static str>,
+    ) {
+        let namespace = namespace_in_project("testing-namespace", Some(annotation), json!({}));
 
         let settings = Settings {
-            downstream_cluster_failure_mode: failure_mode,
+            local_cluster_id: local_cluster_id.to_string(),
+            local_cluster_projects_namespace: local_cluster_projects_namespace
+                .map(|ns| ns.to_string()),
+            ..Default::default()
         };
         let request = KubernetesAdmissionRequest {
             object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
             ..Default::default()
         };
-        let validation_request = ValidationRequest::<Settings> { settings, request };
-        let payload = serde_json::to_string(&validation_request)
-            .expect("cannot serialize validation request");
-
         let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
-        ctx_get_resource.expect::<Project>().times(0);
+        ctx_get_resource
+            .expect::<Project>()
+            .times(usize::from(expected_lookup_namespace.is_some()))
+            .returning(move |req| {
+                if req.namespace.as_deref() != expected_lookup_namespace {
+                    Err(anyhow!(
+                        "it's not searching inside of the expected Namespace"
+                    ))
+                } else {
+                    Ok(Project::default())
+                }
+            });
 
-        let response = validate(payload.as_bytes());
-        assert!(response.is_ok());
-        let validation_response: ValidationResponse = serde_json::from_slice(&response.unwrap())
-            .expect("cannot deserialize validation_response");
+        let validation_response = run(settings, request);
+
+        assert!(validation_response.accepted);
+    }
+
+    #[rstest]
+    #[case(FailureMode::Ignore, json!({}), true)]
+    #[case(FailureMode::Fail, json!({}), false)]
+    #[case(FailureMode::Ignore, json!({"c-m-*": "Fail"}), false)]
+    #[case(FailureMode::Fail, json!({"c-m-abcd1234": "Ignore"}), true)]
+    #[case(FailureMode::Fail, json!({"c-?????": "Ignore"}), false)]
+    #[serial]
+    fn downstream_cluster(
+        #[case] failure_mode: FailureMode,
+        #[case] failure_modes: serde_json::Value,
+        #[case] accepted: bool,
+    ) {
+        let prj_name = "p-abc12".to_string();
+        let cluster_id = "c-m-abcd1234";
+
+        let namespace = namespace_in_project(
+            "testing-namespace",
+            Some(&format!("{cluster_id}:{prj_name}")),
+            json!({}),
+        );
+
+        let settings = Settings {
+            downstream_cluster_failure_mode: failure_mode,
+            downstream_cluster_failure_modes: serde_json::from_value(failure_modes)
+                .expect("cannot deserialize failure modes"),
+            ..Default::default()
+        };
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource.expect::<Project>().times(0);
+
+        let validation_response = run(settings, request);
+
+        assert_eq!(accepted, validation_response.accepted);
+    }
+
+    #[rstest]
+    #[case(
+        FailureMode::Fail,
+        Ok(json!({"p-abc12": r#"{"propagate.hello": "world", "foo": "bar"}"#})),
+        Some(json!({"hello": "world"})),
+        None,
+    )]
+    #[case(FailureMode::Ignore, Ok(json!({"p-zzzzz": "{}"})), None, None)]
+    #[case(FailureMode::Fail, Ok(json!({"p-zzzzz": "{}"})), None, Some(400))]
+    #[case(FailureMode::Fail, Ok(json!({"p-abc12": "not json"})), None, Some(500))]
+    #[case(
+        FailureMode::Fail,
+        Err("configmaps \"project-labels\" not found"),
+        None,
+        Some(404)
+    )]
+    #[case(
+        FailureMode::Ignore,
+        Err("configmaps \"project-labels\" not found"),
+        None,
+        None
+    )]
+    #[serial]
+    fn downstream_cluster_mirror(
+        #[case] failure_mode: FailureMode,
+        #[case] mirror_data: Result<serde_json::Value, &'static str>,
+        #[case] expected_labels: Option<serde_json::Value>,
+        #[case] expected_rejection_code: Option<u16>,
+    ) {
+        let namespace =
+            namespace_in_project("testing-namespace", Some("c-m-abcd1234:p-abc12"), json!({}));
+
+        // the Namespace belongs to a Project of the `c-m-abcd1234` cluster,
+        // while the policy is running inside of another cluster
+        let settings = Settings {
+            local_cluster_id: "c-m-zzzz0000".to_string(),
+            downstream_cluster_failure_mode: failure_mode,
+            downstream_projects_mirror: Some(settings::ProjectsMirror {
+                namespace: "cattle-system".to_string(),
+                name: "project-labels".to_string(),
+            }),
+            ..Default::default()
+        };
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource.expect::<Project>().times(0);
+        ctx_get_resource
+            .expect::<apicore::ConfigMap>()
+            .times(1)
+            .returning(move |req| {
+                if req.namespace.as_deref() != Some("cattle-system")
+                    || req.name != "project-labels"
+                    || !req.disable_cache
+                {
+                    return Err(anyhow!("it's not searching the expected ConfigMap"));
+                }
+                match &mirror_data {
+                    Ok(data) => Ok(apicore::ConfigMap {
+                        data: Some(
+                            serde_json::from_value(data.clone())
+                                .expect("cannot deserialize mirror data"),
+                        ),
+                        ..Default::default()
+                    }),
+                    Err(msg) => Err(anyhow!(*msg)),
+                }
+            });
+
+        let validation_response = run(settings, request);
+
+        assert_eq!(
+            expected_rejection_code.is_none(),
+            validation_response.accepted
+        );
+        assert_eq!(expected_rejection_code, validation_response.code);
+
+        let actual_labels = validation_response.mutated_object.map(|obj| {
+            serde_json::from_value::<apicore::Namespace>(obj)
+                .expect("cannot deserialize mutated Namespace")
+                .metadata
+                .labels
+        });
+        let expected_labels = expected_labels.map(|labels| {
+            Some(
+                serde_json::from_value::<BTreeMap<String, String>>(labels)
+                    .expect("cannot deserialize expected labels"),
+            )
+        });
+        assert_eq!(expected_labels, actual_labels);
+    }
+
+    #[rstest]
+    #[case(Some("local"), EnforcementMode::Reject, true, false)]
+    #[case(None, EnforcementMode::Reject, true, false)]
+    #[case(Some("c-m-abcd1234"), EnforcementMode::Reject, false, false)]
+    #[case(Some("c-m-abcd1234"), EnforcementMode::Warn, true, true)]
+    #[serial]
+    fn project_cluster_mismatch(
+        #[case] cluster_name: Option<&str>,
+        #[case] mode: EnforcementMode,
+        #[case] accepted: bool,
+        #[case] warned: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "apiVersion": "management.cattle.io/v3",
+            "kind": "Project",
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": "local",
+                "labels": {"propagate.hello": "world"},
+            },
+            "spec": {
+                "clusterName": cluster_name,
+                "description": "",
+                "enableProjectMonitoring": false,
+            },
+        }))
+        .expect("cannot deserialize Project");
+
+        let namespace = namespace_in_project("testing-namespace", Some("local:p-abc12"), json!({}));
+
+        let settings = Settings {
+            project_cluster_mismatch_mode: mode,
+            ..Default::default()
+        };
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(1)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
+
+        assert_eq!(accepted, validation_response.accepted);
+        assert_eq!(accepted, validation_response.mutated_object.is_some());
+        assert_eq!(warned, validation_response.warnings.is_some());
+    }
+
+    #[rstest]
+    #[case::valid(json!({"propagate.team": "payments", "cattle.io/creator": "norman"}), true)]
+    #[case::no_labels(json!({}), true)]
+    #[case::invalid(json!({"propagate.team": "not valid"}), false)]
+    #[case::reserved(json!({"propagate.kubernetes.io/metadata.name": "x"}), false)]
+    #[case::collision(json!({"propagate.field.cattle.io/projectId": "p-abc12"}), false)]
+    fn validate_project_labels(#[case] labels: serde_json::Value, #[case] accepted: bool) {
+        let project = json!({
+            "apiVersion": "management.cattle.io/v3",
+            "kind": "Project",
+            "metadata": {"name": "p-abc12", "namespace": "local", "labels": labels},
+            "spec": {"clusterName": "local", "displayName": "payments"},
+        });
+        let request = KubernetesAdmissionRequest {
+            kind: project_kind(),
+            object: project,
+            ..Default::default()
+        };
+        let validation_response = run(Settings::default(), request);
+
+        assert_eq!(accepted, validation_response.accepted);
+        assert!(validation_response.mutated_object.is_none());
+        if !accepted {
+            assert_eq!(Some(400), validation_response.code);
+            assert!(validation_response
+                .message
+                .expect("rejection message is missing")
+                .contains("the Project local:p-abc12 has labels that cannot be propagated"));
+        }
+    }
+
+    #[rstest]
+    #[case::rancher_update(json!({}), json!({}), Ok(()))]
+    #[case::deleting(
+        json!({"metadata": {"deletionTimestamp": "2024-01-01T00:00:00Z", "labels": {"propagate.team": "changed"}}}),
+        json!({}),
+        Ok(())
+    )]
+    #[case::invalid_label_added(
+        json!({"metadata": {"labels": {"propagate.tier": "a b"}}}),
+        json!({}),
+        Err("has labels that cannot be propagated: `propagate.tier`")
+    )]
+    #[case::valid_label_added(
+        json!({"metadata": {"labels": {"propagate.tier": "gold"}}}),
+        json!({}),
+        Ok(())
+    )]
+    #[case::quota_changed(
+        json!({"spec": {"resourceQuota": {"limit": {"pods": "20"}}}}),
+        json!({}),
+        Err("a default Namespace quota is required")
+    )]
+    #[case::quota_usage_changed(
+        json!({"spec": {"resourceQuota": {"usedLimit": {"pods": "5"}}}}),
+        json!({"spec": {"resourceQuota": {"usedLimit": {"pods": "4"}}}}),
+        Ok(())
+    )]
+    #[case::naming_rule_changed(
+        json!({"metadata": {"annotations": {"kubewarden.io/namespace-pattern": "payments-("}}}),
+        json!({}),
+        Err("invalid value of the kubewarden.io/namespace-pattern annotation")
+    )]
+    fn validate_project_update(
+        #[case] changes: serde_json::Value,
+        #[case] old_changes: serde_json::Value,
+        #[case] expected: Result<(), &str>,
+    ) {
+        // a Project that was already invalid before the policy was deployed
+        let invalid_project = || {
+            json!({
+                "apiVersion": "management.cattle.io/v3",
+                "kind": "Project",
+                "metadata": {
+                    "name": "p-abc12",
+                    "namespace": "local",
+                    "labels": {"propagate.team": "not valid"},
+                    "annotations": {"kubewarden.io/namespace-pattern": "("},
+                },
+                "spec": {
+                    "clusterName": "local",
+                    "displayName": "payments",
+                    "resourceQuota": {"limit": {"pods": "10"}},
+                },
+            })
+        };
+        let mut project: Project =
+            serde_json::from_value(invalid_project()).expect("cannot deserialize Project");
+        project.merge_from(serde_json::from_value(changes).expect("cannot deserialize changes"));
+        let mut old_project: Project =
+            serde_json::from_value(invalid_project()).expect("cannot deserialize Project");
+        old_project
+            .merge_from(serde_json::from_value(old_changes).expect("cannot deserialize changes"));
+
+        let request = KubernetesAdmissionRequest {
+            kind: project_kind(),
+            operation: "UPDATE".to_string(),
+            object: serde_json::to_value(project).expect("cannot serialize Project"),
+            old_object: serde_json::to_value(old_project).expect("cannot serialize Project"),
+            ..Default::default()
+        };
+        let validation_response = run(Settings::default(), request);
+
+        match expected {
+            Ok(()) => assert!(
+                validation_response.accepted,
+                "{:?}",
+                validation_response.message
+            ),
+            Err(msg) => {
+                assert!(!validation_response.accepted);
+                let message = validation_response.message.unwrap_or_default();
+                assert!(message.contains(msg), "{message}");
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::warn(json!({"propagate.team": "payments"}), EnforcementMode::Warn, 1, true, true)]
+    #[case::reject(json!({"propagate.team": "payments"}), EnforcementMode::Reject, 1, false, false)]
+    #[case::labels_not_changed(
+        json!({"propagate.team": "billing", "other": "changed"}),
+        EnforcementMode::Reject,
+        0,
+        true,
+        false
+    )]
+    #[serial]
+    fn project_label_drift(
+        #[case] labels: serde_json::Value,
+        #[case] mode: EnforcementMode,
+        #[case] namespace_lookups: usize,
+        #[case] accepted: bool,
+        #[case] warned: bool,
+    ) {
+        let project = |labels: serde_json::Value| {
+            json!({
+                "apiVersion": "management.cattle.io/v3",
+                "kind": "Project",
+                "metadata": {"name": "p-abc12", "namespace": "local", "labels": labels},
+                "spec": {"clusterName": "local"},
+            })
+        };
+        let namespaces = vec![
+            namespace_in_project(
+                "drifting",
+                Some("local:p-abc12"),
+                json!({"team": "billing"}),
+            ),
+            namespace_in_project(
+                "up-to-date",
+                Some("local:p-abc12"),
+                json!({"team": "payments"}),
+            ),
+            namespace_in_project(
+                "other-project",
+                Some("local:p-xyz98"),
+                json!({"team": "billing"}),
+            ),
+        ];
+
+        let settings = Settings {
+            analyze_project_label_changes: true,
+            project_label_drift_mode: mode,
+            ..Default::default()
+        };
+        let request = KubernetesAdmissionRequest {
+            kind: project_kind(),
+            object: project(labels),
+            old_object: project(json!({"propagate.team": "billing"})),
+            ..Default::default()
+        };
+        let ctx_list_all_resources = mock_kubernetes_sdk::list_all_resources_context();
+        ctx_list_all_resources
+            .expect::<apicore::Namespace>()
+            .times(namespace_lookups)
+            .withf(|req| req.label_selector.as_deref() == Some("field.cattle.io/projectId=p-abc12"))
+            .returning(move |_| {
+                Ok(k8s_openapi::List {
+                    items: namespaces.clone(),
+                    metadata: Default::default(),
+                })
+            });
+
+        let validation_response = run(settings, request);
+
+        assert_eq!(accepted, validation_response.accepted);
+        assert_eq!(warned, validation_response.warnings.is_some());
+        let message = validation_response
+            .warnings
+            .and_then(|warnings| warnings.first().cloned())
+            .or(validation_response.message);
+        if let Some(message) = message {
+            assert!(
+                message.ends_with("until they are updated: drifting"),
+                "{message}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::namespaces_left(json!({}), "local", 1, false)]
+    #[case::override_annotation(json!({"project.kubewarden.io/allow-deletion": "true"}), "local", 0, true)]
+    #[case::downstream_project(json!({}), "c-m-abcd1234", 0, true)]
+    #[serial]
+    fn project_deletion(
+        #[case] annotations: serde_json::Value,
+        #[case] cluster_name: &str,
+        #[case] namespace_lookups: usize,
+        #[case] accepted: bool,
+    ) {
+        let project = json!({
+            "apiVersion": "management.cattle.io/v3",
+            "kind": "Project",
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": cluster_name,
+                "annotations": annotations,
+            },
+            "spec": {"clusterName": cluster_name},
+        });
+        let namespaces = vec![
+            namespace_in_project("member", Some("local:p-abc12"), json!({})),
+            namespace_in_project("other-project", Some("local:p-xyz98"), json!({})),
+        ];
+
+        let request = KubernetesAdmissionRequest {
+            kind: project_kind(),
+            operation: "DELETE".to_string(),
+            old_object: project,
+            ..Default::default()
+        };
+        let ctx_list_all_resources = mock_kubernetes_sdk::list_all_resources_context();
+        ctx_list_all_resources
+            .expect::<apicore::Namespace>()
+            .times(namespace_lookups)
+            .withf(|req| req.label_selector.as_deref() == Some("field.cattle.io/projectId=p-abc12"))
+            .returning(move |_| {
+                Ok(k8s_openapi::List {
+                    items: namespaces.clone(),
+                    metadata: Default::default(),
+                })
+            });
+
+        let validation_response = run(Settings::default(), request);
+
+        assert_eq!(accepted, validation_response.accepted);
+        if !accepted {
+            let message = validation_response
+                .message
+                .expect("rejection message is missing");
+            assert!(
+                message.contains("1 Namespaces still belong to it: member."),
+                "{message}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::below_limit(1, None, 1, true)]
+    #[case::limit_reached(2, None, 1, false)]
+    #[case::moved_from_other_project(2, Some("local:p-xyz98"), 1, false)]
+    #[case::already_member(2, Some("local:p-abc12"), 0, true)]
+    #[serial]
+    fn max_namespaces_per_project(
+        #[case] existing_namespaces: usize,
+        #[case] old_project_id: Option<&str>,
+        #[case] namespace_lookups: usize,
+        #[case] accepted: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "metadata": {"name": "p-abc12", "namespace": "local"},
+            "spec": {"clusterName": "local"},
+        }))
+        .expect("cannot deserialize Project");
+        let namespace =
+            |name: &str, project_id: &str| namespace_in_project(name, Some(project_id), json!({}));
+        let mut namespaces: Vec<apicore::Namespace> = (0..existing_namespaces)
+            .map(|i| namespace(&format!("member-{i}"), "local:p-abc12"))
+            .collect();
+        namespaces.push(namespace("other-project", "local:p-xyz98"));
+
+        let settings = Settings {
+            max_namespaces_per_project: Some(2),
+            ..Default::default()
+        };
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(namespace("testing-namespace", "local:p-abc12"))
+                .expect("cannot serialize Namespace"),
+            old_object: old_project_id
+                .map(|project_id| {
+                    serde_json::to_value(namespace("testing-namespace", project_id))
+                        .expect("cannot serialize Namespace")
+                })
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(1)
+            .returning(move |_| Ok(project.clone()));
+        let ctx_list_all_resources = mock_kubernetes_sdk::list_all_resources_context();
+        ctx_list_all_resources
+            .expect::<apicore::Namespace>()
+            .times(namespace_lookups)
+            .withf(|req| req.label_selector.as_deref() == Some("field.cattle.io/projectId=p-abc12"))
+            .returning(move |_| {
+                Ok(k8s_openapi::List {
+                    items: namespaces.clone(),
+                    metadata: Default::default(),
+                })
+            });
+
+        let validation_response = run(settings, request);
+
+        assert_eq!(accepted, validation_response.accepted);
+        if !accepted {
+            assert_eq!(
+                Some(
+                    "the Project local:p-abc12 already has 2 Namespaces, the maximum allowed is 2"
+                        .to_string()
+                ),
+                validation_response.message
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::disabled(false, "bob", true)]
+    #[case::rejected(true, "bob", false)]
+    #[case::exempted(true, "alice", true)]
+    #[serial]
+    fn immutable_propagated_labels(
+        #[case] immutable: bool,
+        #[case] username: &str,
+        #[case] accepted: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": "local",
+                "labels": {"propagate.tier": "gold"},
+            },
+        }))
+        .expect("cannot deserialize Project");
+        let namespace = |labels: serde_json::Value| {
+            namespace_in_project("testing-namespace", Some("local:p-abc12"), labels)
+        };
+
+        let settings: Settings = serde_json::from_value(json!({
+            "immutable_propagated_labels": immutable,
+            "immutable_propagated_labels_exemptions": {"users": ["alice"]},
+        }))
+        .expect("cannot deserialize settings");
+        let request = KubernetesAdmissionRequest {
+            operation: "UPDATE".to_string(),
+            user_info: kubewarden::request::UserInfo {
+                username: username.to_string(),
+                ..Default::default()
+            },
+            object: serde_json::to_value(namespace(json!({"tier": "platinum"})))
+                .expect("cannot serialize Namespace"),
+            old_object: serde_json::to_value(namespace(json!({"tier": "gold"})))
+                .expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(1)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
+
+        assert_eq!(accepted, validation_response.accepted);
+        // accepted changes are reverted by the label propagation
+        assert_eq!(accepted, validation_response.mutated_object.is_some());
+    }
+
+    #[rstest]
+    #[case::exempted_namespace("kube-system", "bob", 0, false)]
+    #[case::exempted_user("team-a", "admin", 0, false)]
+    #[case::not_exempted("team-a", "bob", 1, true)]
+    #[serial]
+    fn exemptions(
+        #[case] namespace_name: &str,
+        #[case] username: &str,
+        #[case] project_lookups: usize,
+        #[case] mutated: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": "local",
+                "labels": {"propagate.tier": "gold"},
+            },
+        }))
+        .expect("cannot deserialize Project");
+        let namespace = namespace_in_project(namespace_name, Some("local:p-abc12"), json!({}));
+
+        let settings: Settings = serde_json::from_value(json!({
+            "exemptions": {"users": ["admin"], "namespaces": ["kube-*", "cattle-*"]},
+        }))
+        .expect("cannot deserialize settings");
+        let request = KubernetesAdmissionRequest {
+            user_info: kubewarden::request::UserInfo {
+                username: username.to_string(),
+                ..Default::default()
+            },
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(project_lookups)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
+
+        assert!(validation_response.accepted);
+        assert_eq!(mutated, validation_response.mutated_object.is_some());
+    }
+
+    #[rstest]
+    #[case::matched(json!({"propagation-beta": "true"}), 1, true)]
+    #[case::not_matched(json!({"propagation-beta": "false"}), 0, false)]
+    #[serial]
+    fn namespace_selector(
+        #[case] labels: serde_json::Value,
+        #[case] project_lookups: usize,
+        #[case] mutated: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": "local",
+                "labels": {"propagate.tier": "gold"},
+            },
+        }))
+        .expect("cannot deserialize Project");
+        let namespace = namespace_in_project("testing-namespace", Some("local:p-abc12"), labels);
+
+        let settings: Settings = serde_json::from_value(json!({
+            "namespace_selector": {"matchLabels": {"propagation-beta": "true"}},
+        }))
+        .expect("cannot deserialize settings");
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(project_lookups)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
+
+        assert!(validation_response.accepted);
+        assert_eq!(mutated, validation_response.mutated_object.is_some());
+    }
+
+    #[rstest]
+    #[case::assigned("team-a", "CREATE", 1, true)]
+    #[case::excluded("kube-public", "CREATE", 0, false)]
+    #[case::update("team-a", "UPDATE", 0, false)]
+    #[serial]
+    fn default_project(
+        #[case] namespace_name: &str,
+        #[case] operation: &str,
+        #[case] project_lookups: usize,
+        #[case] assigned: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": "local",
+                "labels": {"propagate.tier": "gold"},
+            },
+        }))
+        .expect("cannot deserialize Project");
+        let namespace = namespace_in_project(namespace_name, None, json!({}));
+
+        let settings: Settings = serde_json::from_value(json!({
+            "default_project": "local:p-abc12",
+            "default_project_exclusions": ["kube-*"],
+        }))
+        .expect("cannot deserialize settings");
+        let request = KubernetesAdmissionRequest {
+            operation: operation.to_string(),
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(project_lookups)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
+
+        assert!(validation_response.accepted);
+        let patched = validation_response.mutated_object.map(|obj| {
+            serde_json::from_value::<apicore::Namespace>(obj)
+                .expect("cannot deserialize mutated Namespace")
+        });
+        assert_eq!(assigned, patched.is_some());
+        if let Some(patched) = patched {
+            assert_eq!(
+                Some(BTreeMap::from([(
+                    RANCHER_PROJECT_ID_LABEL.to_string(),
+                    "local:p-abc12".to_string()
+                )])),
+                patched.metadata.annotations
+            );
+            assert_eq!(
+                Some(BTreeMap::from([
+                    (RANCHER_PROJECT_ID_LABEL.to_string(), "p-abc12".to_string()),
+                    ("tier".to_string(), "gold".to_string()),
+                ])),
+                patched.metadata.labels
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::unique("payments", 1, Ok("p-abc12"))]
+    #[case::ambiguous("billing", 0, Err("shared by the Projects `p-def34`, `p-ghi56`"))]
+    #[case::unknown("shipping", 0, Err("no Project has this display name"))]
+    #[case::unnamed("support", 0, Err("the matching Project has no name"))]
+    #[serial]
+    fn project_name_label(
+        #[case] display_name: &str,
+        #[case] project_lookups: usize,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let projects: Vec<Project> = serde_json::from_value(json!([
+            {
+                "metadata": {
+                    "name": "p-abc12",
+                    "namespace": "local",
+                    "labels": {"propagate.team": "payments"},
+                },
+                "spec": {"displayName": "payments"},
+            },
+            {"metadata": {"name": "p-def34", "namespace": "local"}, "spec": {"displayName": "billing"}},
+            {"metadata": {"name": "p-ghi56", "namespace": "local"}, "spec": {"displayName": "billing"}},
+            {"metadata": {"namespace": "local"}, "spec": {"displayName": "support"}},
+        ]))
+        .expect("cannot deserialize Projects");
+        let project = projects[0].clone();
+        let namespace = namespace_in_project(
+            "team-a",
+            None,
+            json!({project_name::PROJECT_NAME_LABEL: display_name}),
+        );
+
+        let settings = Settings::default();
+        let request = KubernetesAdmissionRequest {
+            operation: "CREATE".to_string(),
+            object: serde_json::to_value(namespace).expect("cannot serialize Namespace"),
+            ..Default::default()
+        };
+        let ctx_list_resources = mock_kubernetes_sdk::list_resources_by_namespace_context();
+        ctx_list_resources
+            .expect::<Project>()
+            .times(1)
+            .withf(|req| req.namespace == "local")
+            .returning(move |_| {
+                Ok(k8s_openapi::List {
+                    items: projects.clone(),
+                    metadata: Default::default(),
+                })
+            });
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(project_lookups)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
+
+        match expected {
+            Ok(project) => {
+                assert!(validation_response.accepted);
+                let patched: apicore::Namespace = serde_json::from_value(
+                    validation_response
+                        .mutated_object
+                        .expect("the Namespace should be mutated"),
+                )
+                .expect("cannot deserialize mutated Namespace");
+                assert_eq!(
+                    Some(&format!("local:{project}")),
+                    patched
+                        .metadata
+                        .annotations
+                        .unwrap_or_default()
+                        .get(RANCHER_PROJECT_ID_LABEL)
+                );
+                let labels = patched.metadata.labels.unwrap_or_default();
+                assert_eq!(
+                    Some(&project.to_string()),
+                    labels.get(RANCHER_PROJECT_ID_LABEL)
+                );
+                assert_eq!(Some(&"payments".to_string()), labels.get("team"));
+            }
+            Err(msg) => {
+                assert!(!validation_response.accepted);
+                let message = validation_response.message.unwrap_or_default();
+                assert!(message.contains(msg), "{message}");
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::joining(false, false)]
+    #[case::already_member(true, true)]
+    #[serial]
+    fn namespace_of_project_with_invalid_labels(
+        #[case] already_member: bool,
+        #[case] accepted: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": "local",
+                "labels": {"propagate.tier": "platinum", "propagate.team": "payments"},
+            },
+        }))
+        .expect("cannot deserialize Project");
+        let namespace = namespace_in_project("testing-namespace", Some("local:p-abc12"), json!({}));
+
+        let settings: Settings = serde_json::from_value(json!({
+            "allowed_label_values": {"tier": ["gold", "silver"]},
+        }))
+        .expect("cannot deserialize settings");
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(&namespace).expect("cannot serialize Namespace"),
+            old_object: if already_member {
+                serde_json::to_value(&namespace).expect("cannot serialize Namespace")
+            } else {
+                serde_json::Value::Null
+            },
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(1)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
+
+        assert_eq!(accepted, validation_response.accepted);
+        let message = validation_response
+            .warnings
+            .and_then(|warnings| warnings.first().cloned())
+            .or(validation_response.message)
+            .expect("rejection or warning message is missing");
+        assert!(
+            message.contains("value `platinum` is not allowed"),
+            "{message}"
+        );
+        if accepted {
+            // the valid labels are still propagated
+            let patched: apicore::Namespace = serde_json::from_value(
+                validation_response
+                    .mutated_object
+                    .expect("the Namespace should be mutated"),
+            )
+            .expect("cannot deserialize mutated Namespace");
+            assert_eq!(
+                Some(BTreeMap::from([(
+                    "team".to_string(),
+                    "payments".to_string()
+                )])),
+                patched.metadata.labels
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::ready("True", None, false, EnforcementMode::Reject, true, false)]
+    #[case::initializing_reject("Unknown", None, false, EnforcementMode::Reject, false, false)]
+    #[case::initializing_warn("Unknown", None, false, EnforcementMode::Warn, true, true)]
+    #[case::deleted_reject(
+        "True",
+        Some("2024-05-02T14:40:19Z"),
+        false,
+        EnforcementMode::Reject,
+        false,
+        false
+    )]
+    #[case::already_member(
+        "True",
+        Some("2024-05-02T14:40:19Z"),
+        true,
+        EnforcementMode::Reject,
+        true,
+        false
+    )]
+    #[serial]
+    fn project_not_ready(
+        #[case] roles_populated: &str,
+        #[case] deletion_timestamp: Option<&str>,
+        #[case] already_member: bool,
+        #[case] mode: EnforcementMode,
+        #[case] accepted: bool,
+        #[case] warned: bool,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "apiVersion": "management.cattle.io/v3",
+            "kind": "Project",
+            "metadata": {
+                "name": "p-abc12",
+                "namespace": "local",
+                "labels": {"propagate.hello": "world"},
+                "deletionTimestamp": deletion_timestamp,
+            },
+            "spec": {"clusterName": "local"},
+            "status": {"conditions": [
+                {"type": "BackingNamespaceCreated", "status": "True"},
+                {"type": "InitialRolesPopulated", "status": roles_populated},
+            ]},
+        }))
+        .expect("cannot deserialize Project");
+
+        let namespace = namespace_in_project("testing-namespace", Some("local:p-abc12"), json!({}));
+        let old_object = if already_member {
+            serde_json::to_value(&namespace).expect("cannot serialize Namespace")
+        } else {
+            serde_json::Value::Null
+        };
+
+        let settings = Settings {
+            project_not_ready_mode: mode,
+            ..Default::default()
+        };
+        let request = KubernetesAdmissionRequest {
+            object: serde_json::to_value(&namespace).expect("cannot serialize Namespace"),
+            old_object,
+            ..Default::default()
+        };
+        let ctx_get_resource = mock_kubernetes_sdk::get_resource_context();
+        ctx_get_resource
+            .expect::<Project>()
+            .times(1)
+            .returning(move |_| Ok(project.clone()));
+
+        let validation_response = run(settings, request);
 
         assert_eq!(accepted, validation_response.accepted);
+        assert_eq!(warned, validation_response.warnings.is_some());
     }
 }
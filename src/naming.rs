@@ -0,0 +1,175 @@
+use k8s_openapi::api::core::v1 as apicore;
+use regex::Regex;
+
+use crate::custom_resources::Project;
+use crate::errors::{PolicyError, PolicyResult};
+use crate::project_id::ProjectId;
+
+/// Project label or annotation holding the prefix the names of its Namespaces
+/// must start with. Label values cannot end with `-`, the annotation must be
+/// used for prefixes like `payments-`
+pub(crate) const NAMESPACE_PREFIX_KEY: &str = "kubewarden.io/namespace-prefix";
+
+/// Project annotation holding the regular expression the names of its
+/// Namespaces must match. The whole name must match
+pub(crate) const NAMESPACE_PATTERN_ANNOTATION: &str = "kubewarden.io/namespace-pattern";
+
+/// A naming rule declared by a Project
+#[derive(Debug)]
+pub(crate) enum NamingRule {
+    Prefix(String),
+    /// The pattern as written by the user, and its anchored version
+    Pattern {
+        pattern: String,
+        regex: Regex,
+    },
+}
+
+impl NamingRule {
+    /// Read the naming rules declared by the Project. The prefix is looked up
+    /// inside of the annotations first, then inside of the labels
+    pub fn from_project(project: &Project) -> PolicyResult<Vec<NamingRule>> {
+        let annotations = project.metadata.annotations.clone().unwrap_or_default();
+        let labels = project.metadata.labels.clone().unwrap_or_default();
+        let mut rules = Vec::new();
+
+        if let Some(prefix) = annotations
+            .get(NAMESPACE_PREFIX_KEY)
+            .or_else(|| labels.get(NAMESPACE_PREFIX_KEY))
+        {
+            rules.push(NamingRule::Prefix(prefix.to_owned()));
+        }
+        if let Some(pattern) = annotations.get(NAMESPACE_PATTERN_ANNOTATION) {
+            let regex = Regex::new(&format!("^(?:{pattern})$")).map_err(|e| {
+                PolicyError::Validation(format!(
+                    "invalid value of the {NAMESPACE_PATTERN_ANNOTATION} annotation of the Project: {e}"
+                ))
+            })?;
+            rules.push(NamingRule::Pattern {
+                pattern: pattern.to_owned(),
+                regex,
+            });
+        }
+        Ok(rules)
+    }
+
+    /// Returns true when the UPDATE of the Project changes its naming rules
+    pub fn changed(project: &Project, old_project: &Project) -> bool {
+        let keys = |project: &Project| {
+            let annotations = project.metadata.annotations.clone().unwrap_or_default();
+            let labels = project.metadata.labels.clone().unwrap_or_default();
+            (
+                annotations.get(NAMESPACE_PREFIX_KEY).cloned(),
+                labels.get(NAMESPACE_PREFIX_KEY).cloned(),
+                annotations.get(NAMESPACE_PATTERN_ANNOTATION).cloned(),
+            )
+        };
+        keys(project) != keys(old_project)
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NamingRule::Prefix(prefix) => name.starts_with(prefix.as_str()),
+            NamingRule::Pattern { regex, .. } => regex.is_match(name),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            NamingRule::Prefix(prefix) => format!("start with `{prefix}`"),
+            NamingRule::Pattern { pattern, .. } => format!("match the `{pattern}` pattern"),
+        }
+    }
+}
+
+/// Ensure the name of the Namespace joining the Project follows the naming
+/// rules declared by the Project
+pub(crate) fn check_namespace_name(
+    project_id: &ProjectId,
+    project: &Project,
+    namespace: &apicore::Namespace,
+) -> PolicyResult<()> {
+    let name = namespace.metadata.name.as_deref().unwrap_or_default();
+    let violated: Vec<String> = NamingRule::from_project(project)?
+        .iter()
+        .filter(|rule| !rule.matches(name))
+        .map(NamingRule::describe)
+        .collect();
+    if violated.is_empty() {
+        return Ok(());
+    }
+    Err(PolicyError::Validation(format!(
+        "the name of the Namespaces of the Project {project_id} must {}, `{name}` does not",
+        violated.join(" and ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_outcome;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case::no_rules(json!({}), "anything", Ok(()))]
+    #[case::prefix_label(json!({"labels": {NAMESPACE_PREFIX_KEY: "payments"}}), "payments-api", Ok(()))]
+    #[case::prefix_annotation(
+        json!({"annotations": {NAMESPACE_PREFIX_KEY: "payments-"}}),
+        "billing-api",
+        Err("must start with `payments-`, `billing-api` does not")
+    )]
+    #[case::annotation_wins(
+        json!({
+            "labels": {NAMESPACE_PREFIX_KEY: "billing"},
+            "annotations": {NAMESPACE_PREFIX_KEY: "payments-"},
+        }),
+        "billing-api",
+        Err("must start with `payments-`")
+    )]
+    #[case::pattern(
+        json!({"annotations": {NAMESPACE_PATTERN_ANNOTATION: "payments-(dev|prod)"}}),
+        "payments-dev",
+        Ok(())
+    )]
+    #[case::pattern_is_anchored(
+        json!({"annotations": {NAMESPACE_PATTERN_ANNOTATION: "payments-(dev|prod)"}}),
+        "payments-dev-old",
+        Err("must match the `payments-(dev|prod)` pattern")
+    )]
+    #[case::both_rules(
+        json!({
+            "labels": {NAMESPACE_PREFIX_KEY: "payments"},
+            "annotations": {NAMESPACE_PATTERN_ANNOTATION: "[a-z]+-prod"},
+        }),
+        "billing-dev",
+        Err("must start with `payments` and match the `[a-z]+-prod` pattern")
+    )]
+    #[case::invalid_pattern(
+        json!({"annotations": {NAMESPACE_PATTERN_ANNOTATION: "payments-("}}),
+        "payments-dev",
+        Err("invalid value of the kubewarden.io/namespace-pattern annotation")
+    )]
+    fn namespace_name(
+        #[case] metadata: serde_json::Value,
+        #[case] name: &str,
+        #[case] expected: Result<(), &str>,
+    ) {
+        let mut project: Project = serde_json::from_value(json!({"metadata": metadata}))
+            .expect("cannot deserialize Project");
+        project.metadata.name = Some("p-abc12".to_string());
+        let namespace = apicore::Namespace {
+            metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let project_id: ProjectId = "local:p-abc12".parse().expect("valid project id");
+
+        assert_outcome(
+            check_namespace_name(&project_id, &project, &namespace),
+            expected,
+        );
+    }
+}
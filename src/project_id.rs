@@ -0,0 +1,286 @@
+use std::fmt;
+use std::str::FromStr;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+use crate::errors::{PolicyError, PolicyResult};
+use crate::RANCHER_PROJECT_ID_LABEL;
+
+/// The id Rancher gives to the cluster where Rancher Manager is running
+pub(crate) const LOCAL_CLUSTER_ID: &str = "local";
+
+/// Identify a Rancher Project, as written inside of the `field.cattle.io/projectId`
+/// annotation: `<cluster id>:<project id>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProjectId {
+    /// Id of the cluster that owns the Project, e.g. `local` or `c-m-abcd1234`.
+    /// This is also the Namespace where the Project object is defined
+    pub cluster_id: String,
+    /// Name of the Project object, e.g. `p-5fcf4`
+    pub project: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ProjectIdError {
+    Empty,
+    Whitespace {
+        position: usize,
+    },
+    MissingSeparator,
+    TooManySeparators {
+        count: usize,
+    },
+    /// The label holds only the project id, without the cluster id
+    SeparatorInLabel,
+    InvalidClusterId(String),
+    InvalidProjectId(String),
+}
+
+impl fmt::Display for ProjectIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectIdError::Empty => write!(f, "the value is empty"),
+            ProjectIdError::Whitespace { position } => {
+                write!(f, "unexpected whitespace at position {position}")
+            }
+            ProjectIdError::MissingSeparator => write!(
+                f,
+                "the `:` separator is missing, expected `<cluster id>:<project id>`"
+            ),
+            ProjectIdError::TooManySeparators { count } => write!(
+                f,
+                "found {count} `:` separators, expected only one between `<cluster id>` and `<project id>`"
+            ),
+            ProjectIdError::SeparatorInLabel => write!(
+                f,
+                "the label holds only the project id, `:` is not allowed"
+            ),
+            ProjectIdError::InvalidClusterId(id) => write!(
+                f,
+                "`{id}` is not a valid cluster id, expected `{LOCAL_CLUSTER_ID}`, `c-xxxxx` or `c-m-xxxxxxxx`"
+            ),
+            ProjectIdError::InvalidProjectId(id) => {
+                write!(f, "`{id}` is not a valid project id, expected `p-xxxxx`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProjectIdError {}
+
+/// Returns true when `value` is made of `len` lowercase alphanumeric characters
+fn is_random_suffix(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+/// Ensure the cluster id follows one of the formats used by Rancher:
+/// `local`, `c-xxxxx` or `c-m-xxxxxxxx`
+pub(crate) fn validate_cluster_id(cluster_id: &str) -> Result<(), ProjectIdError> {
+    let valid = cluster_id == LOCAL_CLUSTER_ID
+        || cluster_id
+            .strip_prefix("c-m-")
+            .is_some_and(|suffix| is_random_suffix(suffix, 8))
+        || cluster_id
+            .strip_prefix("c-")
+            .is_some_and(|suffix| is_random_suffix(suffix, 5));
+    if valid {
+        Ok(())
+    } else {
+        Err(ProjectIdError::InvalidClusterId(cluster_id.to_owned()))
+    }
+}
+
+/// Ensure the project id follows the format used by Rancher: `p-xxxxx`
+pub(crate) fn validate_project(project: &str) -> Result<(), ProjectIdError> {
+    if project
+        .strip_prefix("p-")
+        .is_some_and(|suffix| is_random_suffix(suffix, 5))
+    {
+        Ok(())
+    } else {
+        Err(ProjectIdError::InvalidProjectId(project.to_owned()))
+    }
+}
+
+fn ensure_not_blank(value: &str) -> Result<(), ProjectIdError> {
+    if value.is_empty() {
+        return Err(ProjectIdError::Empty);
+    }
+    match value.char_indices().find(|(_, c)| c.is_whitespace()) {
+        Some((position, _)) => Err(ProjectIdError::Whitespace { position }),
+        None => Ok(()),
+    }
+}
+
+impl ProjectId {
+    /// Build the id starting from the value of the `field.cattle.io/projectId`
+    /// label. The label holds only the project id, the cluster is the one
+    /// where the Namespace is defined
+    pub fn from_label(value: &str, cluster_id: &str) -> Result<Self, ProjectIdError> {
+        ensure_not_blank(value)?;
+        if value.contains(':') {
+            return Err(ProjectIdError::SeparatorInLabel);
+        }
+        validate_project(value)?;
+
+        Ok(ProjectId {
+            cluster_id: cluster_id.to_owned(),
+            project: value.to_owned(),
+        })
+    }
+
+    /// Find the Project a Namespace belongs to. The `field.cattle.io/projectId`
+    /// annotation is looked up first, the label with the same name is used
+    /// as fallback
+    pub fn from_metadata(metadata: &ObjectMeta, cluster_id: &str) -> PolicyResult<Option<Self>> {
+        if let Some(value) = metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(RANCHER_PROJECT_ID_LABEL))
+        {
+            return value.parse::<ProjectId>().map(Some).map_err(|source| {
+                PolicyError::InvalidProjectId {
+                    origin: "annotation",
+                    value: value.to_owned(),
+                    source,
+                }
+            });
+        }
+
+        metadata
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(RANCHER_PROJECT_ID_LABEL))
+            .map(|value| {
+                ProjectId::from_label(value, cluster_id).map_err(|source| {
+                    PolicyError::InvalidProjectId {
+                        origin: "label",
+                        value: value.to_owned(),
+                        source,
+                    }
+                })
+            })
+            .transpose()
+    }
+}
+
+impl FromStr for ProjectId {
+    type Err = ProjectIdError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ensure_not_blank(value)?;
+
+        let tokens: Vec<&str> = value.split(':').collect();
+        let (cluster_id, project) = match tokens.as_slice() {
+            [_] => return Err(ProjectIdError::MissingSeparator),
+            [cluster_id, project] => (*cluster_id, *project),
+            _ => {
+                return Err(ProjectIdError::TooManySeparators {
+                    count: tokens.len() - 1,
+                })
+            }
+        };
+        validate_cluster_id(cluster_id)?;
+        validate_project(project)?;
+
+        Ok(ProjectId {
+            cluster_id: cluster_id.to_owned(),
+            project: project.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for ProjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.cluster_id, self.project)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::collections::BTreeMap;
+
+    #[rstest]
+    #[case("local:p-5fcf4", Ok(("local", "p-5fcf4")))]
+    #[case("c-7xb2m:p-5fcf4", Ok(("c-7xb2m", "p-5fcf4")))]
+    #[case("c-m-4xq2lr7c:p-5fcf4", Ok(("c-m-4xq2lr7c", "p-5fcf4")))]
+    #[case("", Err(ProjectIdError::Empty))]
+    #[case(" local:p-5fcf4", Err(ProjectIdError::Whitespace { position: 0 }))]
+    #[case("local: p-5fcf4", Err(ProjectIdError::Whitespace { position: 6 }))]
+    #[case("p-5fcf4", Err(ProjectIdError::MissingSeparator))]
+    #[case("local:p-5fcf4:", Err(ProjectIdError::TooManySeparators { count: 2 }))]
+    #[case("local::p-5fcf4", Err(ProjectIdError::TooManySeparators { count: 2 }))]
+    #[case(
+        "downstream:p-5fcf4",
+        Err(ProjectIdError::InvalidClusterId("downstream".to_string()))
+    )]
+    #[case("c-m-123:p-5fcf4", Err(ProjectIdError::InvalidClusterId("c-m-123".to_string())))]
+    #[case("c-ABCDE:p-5fcf4", Err(ProjectIdError::InvalidClusterId("c-ABCDE".to_string())))]
+    #[case(":p-5fcf4", Err(ProjectIdError::InvalidClusterId("".to_string())))]
+    #[case("local:", Err(ProjectIdError::InvalidProjectId("".to_string())))]
+    #[case("local:project", Err(ProjectIdError::InvalidProjectId("project".to_string())))]
+    fn parse_project_id(
+        #[case] value: &str,
+        #[case] expected: Result<(&str, &str), ProjectIdError>,
+    ) {
+        let expected = expected.map(|(cluster_id, project)| ProjectId {
+            cluster_id: cluster_id.to_string(),
+            project: project.to_string(),
+        });
+        assert_eq!(expected, value.parse::<ProjectId>());
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        let id: ProjectId = "c-m-4xq2lr7c:p-5fcf4".parse().expect("valid id");
+        assert_eq!("c-m-4xq2lr7c:p-5fcf4", id.to_string());
+    }
+
+    #[rstest]
+    #[case(Some("local:p-aaaaa"), Some("p-bbbbb"), Ok(Some("local:p-aaaaa")))]
+    #[case(None, Some("p-bbbbb"), Ok(Some("local:p-bbbbb")))]
+    #[case(None, None, Ok(None))]
+    #[case(
+        None,
+        Some("local:p-bbbbb"),
+        Err(("label", "the label holds only the project id, `:` is not allowed"))
+    )]
+    #[case(Some("local"), None, Err(("annotation", "the `:` separator is missing")))]
+    fn project_id_from_metadata(
+        #[case] annotation: Option<&str>,
+        #[case] label: Option<&str>,
+        #[case] expected: Result<Option<&str>, (&str, &str)>,
+    ) {
+        let to_map = |value: Option<&str>| {
+            value.map(|v| BTreeMap::from([(RANCHER_PROJECT_ID_LABEL.to_string(), v.to_string())]))
+        };
+        let metadata = ObjectMeta {
+            annotations: to_map(annotation),
+            labels: to_map(label),
+            ..Default::default()
+        };
+
+        match (
+            ProjectId::from_metadata(&metadata, LOCAL_CLUSTER_ID),
+            expected,
+        ) {
+            (Ok(actual), Ok(expected)) => {
+                assert_eq!(expected, actual.map(|id| id.to_string()).as_deref())
+            }
+            (
+                Err(err @ PolicyError::InvalidProjectId { origin, .. }),
+                Err((expected_origin, expected_msg)),
+            ) => {
+                assert_eq!(expected_origin, origin);
+                let msg = err.to_string();
+                assert!(msg.contains(expected_msg), "{msg}");
+            }
+            (actual, expected) => panic!("expected {expected:?}, got {actual:?}"),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use kubewarden::host_capabilities::kubernetes::ListResourcesByNamespaceRequest;
+
+use crate::custom_resources::Project;
+use crate::errors::{Lookup, PolicyError, PolicyResult};
+use crate::list_resources_by_namespace;
+use crate::project_id::ProjectId;
+use crate::RANCHER_PROJECT_ID_LABEL;
+
+/// Namespace label holding the display name of the Project the Namespace
+/// must be assigned to, e.g. `payments`
+pub(crate) const PROJECT_NAME_LABEL: &str = "project.kubewarden.io/name";
+
+/// Find the Project of the local cluster whose display name is `display_name`.
+/// The Projects are listed from the given Namespace, exactly one of them must
+/// have the display name
+pub(crate) fn resolve_project_name(
+    display_name: &str,
+    projects_namespace: &str,
+    local_cluster_id: &str,
+) -> PolicyResult<ProjectId> {
+    let req = ListResourcesByNamespaceRequest {
+        api_version: "management.cattle.io/v3".to_string(),
+        kind: "Project".to_string(),
+        namespace: projects_namespace.to_string(),
+        label_selector: None,
+        field_selector: None,
+    };
+    let projects = list_resources_by_namespace::<Project>(&req)
+        .map_err(|e| PolicyError::from_lookup(Lookup::list_projects(projects_namespace), e))?;
+
+    let project = find_by_display_name(display_name, &projects.items)?;
+    let name = project.metadata.name.clone().ok_or_else(|| {
+        PolicyError::Validation(format!(
+            "the {PROJECT_NAME_LABEL} label references the Project `{display_name}`, but the matching Project has no name"
+        ))
+    })?;
+    Ok(ProjectId {
+        cluster_id: local_cluster_id.to_owned(),
+        project: name,
+    })
+}
+
+fn find_by_display_name<'a>(
+    display_name: &str,
+    projects: &'a [Project],
+) -> PolicyResult<&'a Project> {
+    let matches: Vec<&Project> = projects
+        .iter()
+        .filter(|project| {
+            project
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.display_name.as_deref())
+                == Some(display_name)
+        })
+        .collect();
+    match matches.as_slice() {
+        [project] => Ok(project),
+        [] => Err(PolicyError::Validation(format!(
+            "the {PROJECT_NAME_LABEL} label references the Project `{display_name}`, but no Project has this display name"
+        ))),
+        _ => Err(PolicyError::Validation(format!(
+            "the {PROJECT_NAME_LABEL} label references the Project `{display_name}`, but this display name is shared by the Projects {}. Set the {RANCHER_PROJECT_ID_LABEL} annotation instead",
+            matches
+                .iter()
+                .map(|project| match project.metadata.name.as_deref() {
+                    Some(name) => format!("`{name}`"),
+                    None => "a Project without name".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_outcome;
+    use rstest::*;
+    use serde_json::json;
+
+    fn projects() -> Vec<Project> {
+        serde_json::from_value(json!([
+            {"metadata": {"name": "p-abc12"}, "spec": {"displayName": "payments"}},
+            {"metadata": {"name": "p-def34"}, "spec": {"displayName": "billing"}},
+            {"metadata": {"name": "p-ghi56"}, "spec": {"displayName": "billing"}},
+            {"metadata": {"name": "p-jkl78"}},
+            {"metadata": {"name": "p-mno90"}, "spec": {"displayName": "support"}},
+            {"metadata": {}, "spec": {"displayName": "support"}},
+        ]))
+        .expect("cannot deserialize Projects")
+    }
+
+    #[rstest]
+    #[case::unique("payments", Ok("p-abc12"))]
+    #[case::ambiguous("billing", Err("shared by the Projects `p-def34`, `p-ghi56`"))]
+    #[case::ambiguous_unnamed(
+        "support",
+        Err("shared by the Projects `p-mno90`, a Project without name")
+    )]
+    #[case::unknown("shipping", Err("no Project has this display name"))]
+    #[case::project_id_is_not_a_display_name("p-abc12", Err("no Project has this display name"))]
+    fn find_project(#[case] display_name: &str, #[case] expected: Result<&str, &str>) {
+        let projects = projects();
+        let result = find_by_display_name(display_name, &projects);
+
+        assert_outcome(
+            result.map(|project| project.metadata.name.as_deref()),
+            expected.map(Some),
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use k8s_openapi::api::core::v1 as apicore;
+use kubewarden::host_capabilities::kubernetes::ListAllResourcesRequest;
+
+use crate::custom_resources::Project;
+use crate::errors::{Lookup, PolicyError, PolicyResult};
+use crate::list_all_resources;
+use crate::project_id::ProjectId;
+use crate::RANCHER_PROJECT_ID_LABEL;
+
+/// Maximum number of Namespaces named inside of a warning or rejection message
+const MAX_REPORTED_NAMESPACES: usize = 10;
+
+/// Project annotation that overrides the `max_namespaces_per_project` setting
+pub(crate) const MAX_NAMESPACES_ANNOTATION: &str = "project.kubewarden.io/max-namespaces";
+
+/// List the Namespaces of the local cluster that belong to the given Project.
+///
+/// Rancher sets both the `field.cattle.io/projectId` annotation and label,
+/// the Namespaces are selected by the label, then the ones whose annotation
+/// points to another Project are filtered out
+pub(crate) fn list_project_namespaces(
+    project_id: &ProjectId,
+    local_cluster_id: &str,
+) -> PolicyResult<Vec<apicore::Namespace>> {
+    let req = ListAllResourcesRequest {
+        api_version: "v1".to_string(),
+        kind: "Namespace".to_string(),
+        label_selector: Some(format!("{RANCHER_PROJECT_ID_LABEL}={}", project_id.project)),
+        field_selector: None,
+    };
+    let namespaces = list_all_resources::<apicore::Namespace>(&req)
+        .map_err(|e| PolicyError::from_lookup(Lookup::list_namespaces(), e))?;
+
+    Ok(namespaces
+        .items
+        .into_iter()
+        .filter(|namespace| {
+            ProjectId::from_metadata(&namespace.metadata, local_cluster_id)
+                .ok()
+                .flatten()
+                .is_some_and(|id| &id == project_id)
+        })
+        .collect())
+}
+
+/// The maximum number of Namespaces the Project can have: the value of its
+/// `project.kubewarden.io/max-namespaces` annotation, or the default one taken
+/// from the settings. `None` when the Project has no limit
+pub(crate) fn namespaces_limit(
+    project: &Project,
+    default_limit: Option<u32>,
+) -> PolicyResult<Option<u32>> {
+    match project
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(MAX_NAMESPACES_ANNOTATION))
+    {
+        Some(value) => value.trim().parse().map(Some).map_err(|_| {
+            PolicyError::Validation(format!(
+                "invalid value of the {MAX_NAMESPACES_ANNOTATION} annotation of the Project: `{value}` is not a non-negative integer"
+            ))
+        }),
+        None => Ok(default_limit),
+    }
+}
+
+/// Ensure the Namespace joining the Project does not exceed the maximum
+/// number of Namespaces allowed inside of it
+pub(crate) fn check_namespaces_limit(
+    project_id: &ProjectId,
+    project: &Project,
+    namespace: &apicore::Namespace,
+    default_limit: Option<u32>,
+    local_cluster_id: &str,
+) -> PolicyResult<()> {
+    let limit = match namespaces_limit(project, default_limit)? {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+
+    let others = list_project_namespaces(project_id, local_cluster_id)?
+        .into_iter()
+        .filter(|other| other.metadata.name != namespace.metadata.name)
+        .count();
+    if others < limit as usize {
+        return Ok(());
+    }
+    Err(PolicyError::Validation(format!(
+        "the Project {project_id} already has {others} Namespaces, the maximum allowed is {limit}"
+    )))
+}
+
+/// Format the names of the Namespaces for a message, truncating long lists
+pub(crate) fn describe_namespaces(names: &[String]) -> String {
+    let mut description = names
+        .iter()
+        .take(MAX_REPORTED_NAMESPACES)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if names.len() > MAX_REPORTED_NAMESPACES {
+        description.push_str(&format!(
+            " and {} more",
+            names.len() - MAX_REPORTED_NAMESPACES
+        ));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case::default(json!({}), Some(5), Ok(Some(5)))]
+    #[case::no_limit(json!({}), None, Ok(None))]
+    #[case::override_default(json!({MAX_NAMESPACES_ANNOTATION: "10"}), Some(5), Ok(Some(10)))]
+    #[case::override_no_limit(json!({MAX_NAMESPACES_ANNOTATION: "0"}), None, Ok(Some(0)))]
+    #[case::invalid(json!({MAX_NAMESPACES_ANNOTATION: "ten"}), Some(5), Err(()))]
+    #[case::negative(json!({MAX_NAMESPACES_ANNOTATION: "-1"}), Some(5), Err(()))]
+    fn limit(
+        #[case] annotations: serde_json::Value,
+        #[case] default_limit: Option<u32>,
+        #[case] expected: Result<Option<u32>, ()>,
+    ) {
+        let project: Project = serde_json::from_value(json!({
+            "metadata": {"name": "p-abc12", "namespace": "local", "annotations": annotations},
+        }))
+        .expect("cannot deserialize Project");
+
+        assert_eq!(
+            expected,
+            namespaces_limit(&project, default_limit).map_err(|_| ())
+        );
+    }
+
+    #[rstest]
+    #[case(0, "")]
+    #[case(2, "ns-0, ns-1")]
+    #[case(
+        12,
+        "ns-0, ns-1, ns-2, ns-3, ns-4, ns-5, ns-6, ns-7, ns-8, ns-9 and 2 more"
+    )]
+    fn describe(#[case] count: usize, #[case] expected: &str) {
+        let names: Vec<String> = (0..count).map(|i| format!("ns-{i}")).collect();
+        assert_eq!(expected, describe_namespaces(&names));
+    }
+}
@@ -0,0 +1,375 @@
+use k8s_openapi::api::core::v1 as apicore;
+
+use crate::custom_resources::{NamespaceResourceQuota, Project, ProjectSpec, ResourceQuotaLimit};
+use crate::errors::{PolicyError, PolicyResult};
+use crate::project_id::ProjectId;
+use crate::quota::Quota;
+use crate::resource_defaults::RANCHER_RESOURCE_QUOTA_ANNOTATION;
+
+/// Parse the resource quota requested by the Namespace through the
+/// `field.cattle.io/resourceQuota` annotation
+pub(crate) fn namespace_quota(namespace: &apicore::Namespace) -> PolicyResult<Option<Quota>> {
+    let raw = match quota_annotation(namespace) {
+        Some(raw) => raw,
+        None => return Ok(None),
+    };
+
+    let invalid = |details: String| {
+        PolicyError::Validation(format!(
+            "invalid value of the {RANCHER_RESOURCE_QUOTA_ANNOTATION} annotation: {details}"
+        ))
+    };
+    let quota: NamespaceResourceQuota =
+        serde_json::from_str(raw).map_err(|e| invalid(e.to_string()))?;
+    quota
+        .limit
+        .as_ref()
+        .map(Quota::try_from)
+        .transpose()
+        .map_err(|e| invalid(e.to_string()))
+}
+
+fn quota_annotation(namespace: &apicore::Namespace) -> Option<&String> {
+    namespace
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(RANCHER_RESOURCE_QUOTA_ANNOTATION))
+}
+
+/// Parse one of the limits of the Project quota
+fn project_quota(
+    project_id: &ProjectId,
+    limit: Option<&ResourceQuotaLimit>,
+) -> PolicyResult<Option<Quota>> {
+    limit
+        .map(Quota::try_from)
+        .transpose()
+        .map_err(|e| PolicyError::Deserialization {
+            what: format!("the resource quota of the Project {project_id}"),
+            details: e.to_string(),
+        })
+}
+
+/// Ensure the resource quota requested by the Namespace fits inside of the
+/// quota still available to the Project.
+///
+/// On UPDATE operations, the quota previously requested by the Namespace is
+/// already accounted inside of the Project usage. It's removed before doing
+/// the comparison. The check is skipped when the Namespace stays inside of the
+/// Project and its quota is not changed: the Project could be over its limit,
+/// for example after the limit has been lowered, and that must not block the
+/// unrelated updates of its Namespaces.
+pub(crate) fn check_namespace_quota(
+    project_id: &ProjectId,
+    project: &Project,
+    namespace: &apicore::Namespace,
+    old_namespace: Option<&apicore::Namespace>,
+    old_project_id: Option<&ProjectId>,
+) -> PolicyResult<()> {
+    if let Some(old_namespace) = old_namespace {
+        if old_project_id == Some(project_id)
+            && quota_annotation(old_namespace) == quota_annotation(namespace)
+        {
+            return Ok(());
+        }
+    }
+    let requested = match namespace_quota(namespace)? {
+        Some(requested) => requested,
+        None => return Ok(()),
+    };
+    let project_resource_quota = match project
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.resource_quota.as_ref())
+    {
+        Some(resource_quota) => resource_quota,
+        None => return Ok(()),
+    };
+    let limit = match project_quota(project_id, project_resource_quota.limit.as_ref())? {
+        Some(limit) if !limit.is_empty() => limit,
+        _ => return Ok(()),
+    };
+    let used =
+        project_quota(project_id, project_resource_quota.used_limit.as_ref())?.unwrap_or_default();
+
+    let previously_requested = match old_namespace {
+        Some(old_namespace) if old_project_id == Some(project_id) => {
+            // an invalid annotation has been rejected in the past, there's
+            // nothing to give back to the Project
+            namespace_quota(old_namespace)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        }
+        _ => Quota::default(),
+    };
+    let used_by_others = &used - &previously_requested;
+    let usage = &used_by_others + &requested;
+
+    if usage.fits_within(&limit) {
+        return Ok(());
+    }
+
+    let details: Vec<String> = usage
+        .overflows(&limit)
+        .iter()
+        .map(|overflow| {
+            format!(
+                "{}: requested {}, already used {}, project limit {}",
+                overflow.resource,
+                requested.get(&overflow.resource).unwrap_or_default(),
+                used_by_others.get(&overflow.resource).unwrap_or_default(),
+                overflow.limit,
+            )
+        })
+        .collect();
+    Err(PolicyError::Validation(format!(
+        "the resource quota of the Namespace exceeds the quota available to the Project {project_id}: {}",
+        details.join("; ")
+    )))
+}
+
+/// Returns true when the UPDATE of the Project changes the quotas checked by
+/// [`check_project_quotas`]. The bookkeeping done by Rancher, like the
+/// `usedLimit` of the Project quota, is not considered a change
+pub(crate) fn quotas_changed(spec: Option<&ProjectSpec>, old_spec: Option<&ProjectSpec>) -> bool {
+    let quotas = |spec: Option<&ProjectSpec>| {
+        (
+            spec.and_then(|spec| spec.resource_quota.as_ref())
+                .and_then(|quota| quota.limit.clone()),
+            spec.and_then(|spec| spec.namespace_default_resource_quota.as_ref())
+                .and_then(|quota| quota.limit.clone()),
+            spec.and_then(|spec| spec.container_default_resource_limit.clone()),
+        )
+    };
+    quotas(spec) != quotas(old_spec)
+}
+
+/// Ensure the quotas defined by a Project are consistent, applying the same
+/// rules as the Rancher UI:
+///
+/// * the Project quota and the default Namespace quota must be set together,
+///   and must limit the same resources
+/// * the default Namespace quota cannot exceed the Project quota
+/// * the container default requests cannot exceed the container default limits
+pub(crate) fn check_project_quotas(project_name: &str, spec: &ProjectSpec) -> PolicyResult<()> {
+    let mut issues: Vec<String> = Vec::new();
+    let mut parse = |what: &str, limit: Option<&ResourceQuotaLimit>| {
+        limit
+            .map(Quota::try_from)
+            .transpose()
+            .unwrap_or_else(|e| {
+                issues.push(format!("invalid {what}: {e}"));
+                None
+            })
+            .unwrap_or_default()
+    };
+    let project_limit = parse(
+        "Project quota",
+        spec.resource_quota
+            .as_ref()
+            .and_then(|quota| quota.limit.as_ref()),
+    );
+    let namespace_default = parse(
+        "default Namespace quota",
+        spec.namespace_default_resource_quota
+            .as_ref()
+            .and_then(|quota| quota.limit.as_ref()),
+    );
+
+    match (project_limit.is_empty(), namespace_default.is_empty()) {
+        (false, true) => issues.push(
+            "a default Namespace quota is required when the Project quota is set".to_string(),
+        ),
+        (true, false) => issues.push(
+            "a Project quota is required when the default Namespace quota is set".to_string(),
+        ),
+        _ => {
+            let missing: Vec<&str> = project_limit
+                .resources()
+                .filter(|resource| namespace_default.get(resource).is_none())
+                .collect();
+            if !missing.is_empty() {
+                issues.push(format!(
+                    "the default Namespace quota must limit the resources limited by the Project quota: {}",
+                    missing.join(", ")
+                ));
+            }
+            issues.extend(
+                namespace_default
+                    .overflows(&project_limit)
+                    .iter()
+                    .map(|overflow| format!("default Namespace quota {overflow}")),
+            );
+        }
+    }
+
+    if let Some(container_limit) = &spec.container_default_resource_limit {
+        match Quota::try_from(container_limit) {
+            Ok(container_limit) => {
+                issues.extend(container_limit.resources().filter_map(|resource| {
+                    let limit_resource = format!("limits{}", resource.strip_prefix("requests")?);
+                    let requested = container_limit.get(resource)?;
+                    let limit = container_limit.get(&limit_resource)?;
+                    (requested > limit).then(|| {
+                        format!(
+                            "container default {resource} {requested} exceeds {limit_resource} {limit}"
+                        )
+                    })
+                }));
+            }
+            Err(e) => issues.push(format!("invalid container default resource limit: {e}")),
+        }
+    }
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+    Err(PolicyError::Validation(format!(
+        "the Project {project_name} has inconsistent quotas: {}",
+        issues.join("; ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_outcome;
+    use rstest::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn namespace(quota: Option<serde_json::Value>) -> apicore::Namespace {
+        let mut namespace = apicore::Namespace::default();
+        if let Some(quota) = quota {
+            namespace.metadata.annotations = Some(BTreeMap::from([(
+                RANCHER_RESOURCE_QUOTA_ANNOTATION.to_string(),
+                quota.to_string(),
+            )]));
+        }
+        namespace
+    }
+
+    fn project(resource_quota: serde_json::Value) -> Project {
+        serde_json::from_value(json!({
+            "apiVersion": "management.cattle.io/v3",
+            "kind": "Project",
+            "metadata": {"name": "p-abc12", "namespace": "local"},
+            "spec": {
+                "description": "",
+                "enableProjectMonitoring": false,
+                "resourceQuota": resource_quota,
+            },
+        }))
+        .expect("cannot deserialize Project")
+    }
+
+    #[rstest]
+    #[case::no_quotas(json!({}), Ok(()))]
+    #[case::consistent(
+        json!({
+            "resourceQuota": {"limit": {"limitsCpu": "4", "count/jobs.batch": "10"}},
+            "namespaceDefaultResourceQuota": {"limit": {"limitsCpu": "1", "count/jobs.batch": "10"}},
+            "containerDefaultResourceLimit": {"requestsCpu": "100m", "limitsCpu": "1", "requestsMemory": "1Gi"},
+        }),
+        Ok(())
+    )]
+    #[case::namespace_default_missing(
+        json!({"resourceQuota": {"limit": {"limitsCpu": "4"}}}),
+        Err(vec!["a default Namespace quota is required"])
+    )]
+    #[case::project_quota_missing(
+        json!({
+            "resourceQuota": {"limit": {}},
+            "namespaceDefaultResourceQuota": {"limit": {"limitsCpu": "1"}},
+        }),
+        Err(vec!["a Project quota is required"])
+    )]
+    #[case::resource_not_limited_by_default(
+        json!({
+            "resourceQuota": {"limit": {"limitsCpu": "4", "pods": "100"}},
+            "namespaceDefaultResourceQuota": {"limit": {"limitsCpu": "1"}},
+        }),
+        Err(vec!["must limit the resources limited by the Project quota: pods"])
+    )]
+    #[case::default_exceeds_project(
+        json!({
+            "resourceQuota": {"limit": {"limitsCpu": "4", "limitsMemory": "1Gi"}},
+            "namespaceDefaultResourceQuota": {"limit": {"limitsCpu": "4500m", "limitsMemory": "2Gi"}},
+        }),
+        Err(vec![
+            "default Namespace quota limitsCpu: 4500m exceeds the limit of 4",
+            "default Namespace quota limitsMemory: 2Gi exceeds the limit of 1Gi",
+        ])
+    )]
+    #[case::container_requests_exceed_limits(
+        json!({
+            "containerDefaultResourceLimit": {
+                "requestsCpu": "2", "limitsCpu": "1",
+                "requestsEphemeralStorage": "2Gi", "limitsEphemeralStorage": "1Gi",
+            },
+        }),
+        Err(vec![
+            "container default requestsCpu 2 exceeds limitsCpu 1",
+            "container default requestsEphemeralStorage 2Gi exceeds limitsEphemeralStorage 1Gi",
+        ])
+    )]
+    #[case::invalid_quantity(
+        json!({"containerDefaultResourceLimit": {"limitsCpu": "lots"}}),
+        Err(vec!["invalid container default resource limit"])
+    )]
+    fn project_quotas(#[case] spec: serde_json::Value, #[case] expected: Result<(), Vec<&str>>) {
+        let spec: ProjectSpec = serde_json::from_value(spec).expect("cannot deserialize spec");
+
+        assert_outcome(check_project_quotas("local:p-abc12", &spec), expected);
+    }
+
+    #[rstest]
+    #[case::no_quota_requested(None, None, Ok(()))]
+    #[case::fits(Some(json!({"limit": {"limitsCpu": "1"}})), None, Ok(()))]
+    #[case::unlimited_resource(Some(json!({"limit": {"pods": "1000"}})), None, Ok(()))]
+    #[case::overflow(
+        Some(json!({"limit": {"limitsCpu": "1500m", "limitsMemory": "4Gi", "configMaps": "1"}})),
+        None,
+        Err(vec!["limitsCpu: requested 1500m, already used 1, project limit 2", "limitsMemory: requested 4Gi"])
+    )]
+    #[case::update_gives_back_previous_quota(
+        Some(json!({"limit": {"limitsCpu": "1500m"}})),
+        Some(json!({"limit": {"limitsCpu": "1"}})),
+        Ok(())
+    )]
+    #[case::update_overflow(
+        Some(json!({"limit": {"limitsCpu": "2500m"}})),
+        Some(json!({"limit": {"limitsCpu": "1"}})),
+        Err(vec!["limitsCpu: requested 2500m, already used 0, project limit 2"])
+    )]
+    #[case::update_unchanged_over_quota(
+        Some(json!({"limit": {"limitsCpu": "3"}})),
+        Some(json!({"limit": {"limitsCpu": "3"}})),
+        Ok(())
+    )]
+    #[case::invalid_annotation(Some(json!({"limit": {"limitsCpu": "lots"}})), None, Err(vec!["invalid value"]))]
+    fn check_quota(
+        #[case] requested: Option<serde_json::Value>,
+        #[case] previously_requested: Option<serde_json::Value>,
+        #[case] expected: Result<(), Vec<&str>>,
+    ) {
+        let project_id: ProjectId = "local:p-abc12".parse().expect("valid project id");
+        let project = project(json!({
+            "limit": {"limitsCpu": "2", "limitsMemory": "4Gi"},
+            "usedLimit": {"limitsCpu": "1", "limitsMemory": "1Gi"},
+        }));
+        let old_namespace = previously_requested.map(|quota| namespace(Some(quota)));
+
+        let result = check_namespace_quota(
+            &project_id,
+            &project,
+            &namespace(requested),
+            old_namespace.as_ref(),
+            Some(&project_id),
+        );
+
+        assert_outcome(result, expected);
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+use crate::custom_resources::{ContainerResourceLimit, ResourceQuotaLimit};
+
+/// Number of nano units inside of one unit
+const NANO: i128 = 1_000_000_000;
+
+/// A Kubernetes quantity, like `100m`, `2Gi` or `1e3`, stored as a number
+/// of nano units
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ParsedQuantity(i128);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct QuantityError {
+    pub quantity: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for QuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid quantity `{}`: {}", self.quantity, self.reason)
+    }
+}
+
+impl std::error::Error for QuantityError {}
+
+/// Multiply `value` by `base^exp`, returning `None` on overflow
+fn checked_scale(value: i128, base: i128, exp: u32) -> Option<i128> {
+    base.checked_pow(exp).and_then(|m| value.checked_mul(m))
+}
+
+/// Divide `value` by `10^exp`, rounding up like Kubernetes does
+fn div_ceil_pow10(value: i128, exp: u32) -> i128 {
+    match 10i128.checked_pow(exp) {
+        Some(divisor) => {
+            let quotient = value / divisor;
+            if value % divisor > 0 {
+                quotient + 1
+            } else {
+                quotient
+            }
+        }
+        None => i128::from(value > 0),
+    }
+}
+
+impl FromStr for ParsedQuantity {
+    type Err = QuantityError;
+
+    fn from_str(quantity: &str) -> Result<Self, Self::Err> {
+        let error = |reason| QuantityError {
+            quantity: quantity.to_owned(),
+            reason,
+        };
+
+        let (negative, unsigned) = match quantity.as_bytes().first() {
+            Some(b'-') => (true, &quantity[1..]),
+            Some(b'+') => (false, &quantity[1..]),
+            Some(_) => (false, quantity),
+            None => return Err(error("the quantity is empty")),
+        };
+        let number_len = unsigned
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(unsigned.len());
+        let (number, suffix) = unsigned.split_at(number_len);
+
+        let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(error("the number is missing"));
+        }
+        if fraction.contains('.') {
+            return Err(error("the number has more than one decimal point"));
+        }
+
+        // the value expressed as `mantissa * 10^-scale`
+        let digits = format!("{integer}{fraction}");
+        let mantissa: i128 = digits.parse().map_err(|_| error("the number is too big"))?;
+        let scale = fraction.len() as i32;
+
+        let (decimal_exp, binary_exp): (i32, u32) = match suffix {
+            "" => (0, 0),
+            "n" => (-9, 0),
+            "u" => (-6, 0),
+            "m" => (-3, 0),
+            "k" => (3, 0),
+            "M" => (6, 0),
+            "G" => (9, 0),
+            "T" => (12, 0),
+            "P" => (15, 0),
+            "E" => (18, 0),
+            "Ki" => (0, 1),
+            "Mi" => (0, 2),
+            "Gi" => (0, 3),
+            "Ti" => (0, 4),
+            "Pi" => (0, 5),
+            "Ei" => (0, 6),
+            exponent if exponent.starts_with(['e', 'E']) => {
+                let exp: i32 = exponent[1..]
+                    .parse()
+                    .map_err(|_| error("the exponent is not a valid integer"))?;
+                (exp, 0)
+            }
+            _ => return Err(error("unknown suffix")),
+        };
+
+        // 1Ki = 2^10, 1Mi = 2^20, ...
+        let value = checked_scale(mantissa, 2, binary_exp * 10)
+            .ok_or_else(|| error("the quantity is too big"))?;
+
+        // bring the value to nano units: value * 10^(9 + decimal_exp - scale)
+        let exp = decimal_exp
+            .checked_add(9)
+            .and_then(|exp| exp.checked_sub(scale))
+            .ok_or_else(|| error("the quantity is too big"))?;
+        let nanos = if exp >= 0 {
+            checked_scale(value, 10, exp as u32).ok_or_else(|| error("the quantity is too big"))?
+        } else {
+            div_ceil_pow10(value, exp.unsigned_abs())
+        };
+
+        Ok(ParsedQuantity(if negative { -nanos } else { nanos }))
+    }
+}
+
+/// Whole multiples of 1024 get the biggest binary suffix, e.g. `2Gi` instead
+/// of `2147483648`, fractions get a decimal suffix, e.g. `500m`
+impl fmt::Display for ParsedQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 % NANO == 0 {
+            let mut units = self.0 / NANO;
+            let mut suffix = "";
+            if units != 0 {
+                for binary_suffix in ["Ki", "Mi", "Gi", "Ti", "Pi", "Ei"] {
+                    if units % 1024 != 0 {
+                        break;
+                    }
+                    units /= 1024;
+                    suffix = binary_suffix;
+                }
+            }
+            write!(f, "{units}{suffix}")
+        } else if self.0 % 1_000_000 == 0 {
+            write!(f, "{}m", self.0 / 1_000_000)
+        } else if self.0 % 1_000 == 0 {
+            write!(f, "{}u", self.0 / 1_000)
+        } else {
+            write!(f, "{}n", self.0)
+        }
+    }
+}
+
+impl Add for ParsedQuantity {
+    type Output = ParsedQuantity;
+
+    fn add(self, other: Self) -> Self::Output {
+        ParsedQuantity(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for ParsedQuantity {
+    type Output = ParsedQuantity;
+
+    fn sub(self, other: Self) -> Self::Output {
+        ParsedQuantity(self.0.saturating_sub(other.0))
+    }
+}
+
+/// A resource whose usage goes beyond its limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Overflow {
+    pub resource: String,
+    pub requested: ParsedQuantity,
+    pub limit: ParsedQuantity,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} exceeds the limit of {}",
+            self.resource, self.requested, self.limit
+        )
+    }
+}
+
+/// Typed version of `ResourceQuotaLimit`, indexed by the name of the resource.
+///
+/// Resources that are not set are not part of the quota. When the quota
+/// is used as a limit, these resources are unlimited, which is different
+/// from having a limit of 0. When the quota is used to track consumption,
+/// these resources are not consumed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Quota(BTreeMap<String, ParsedQuantity>);
+
+impl Quota {
+    /// The amount of the resource, `None` when the resource is not set
+    pub fn get(&self, resource: &str) -> Option<ParsedQuantity> {
+        self.0.get(resource).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The names of the resources that are set
+    pub fn resources(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// List the resources of this quota that go beyond the given limit.
+    /// Resources that are not set inside of the limit are unlimited
+    pub fn overflows(&self, limit: &Quota) -> Vec<Overflow> {
+        self.0
+            .iter()
+            .filter_map(|(resource, requested)| {
+                limit
+                    .get(resource)
+                    .filter(|limit| requested > limit)
+                    .map(|limit| Overflow {
+                        resource: resource.clone(),
+                        requested: *requested,
+                        limit,
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns true when no resource of this quota goes beyond the given limit
+    pub fn fits_within(&self, limit: &Quota) -> bool {
+        self.overflows(limit).is_empty()
+    }
+}
+
+impl FromIterator<(String, ParsedQuantity)> for Quota {
+    fn from_iter<T: IntoIterator<Item = (String, ParsedQuantity)>>(iter: T) -> Self {
+        Quota(iter.into_iter().collect())
+    }
+}
+
+impl Add for &Quota {
+    type Output = Quota;
+
+    /// Sum the resources set by both the quotas. Resources that are set only
+    /// by one of them are copied over
+    fn add(self, other: Self) -> Self::Output {
+        let mut sum = self.clone();
+        for (resource, quantity) in &other.0 {
+            sum.0
+                .entry(resource.clone())
+                .and_modify(|v| *v = *v + *quantity)
+                .or_insert(*quantity);
+        }
+        sum
+    }
+}
+
+impl Sub for &Quota {
+    type Output = Quota;
+
+    /// Subtract the resources of `other` from the ones of this quota. Only
+    /// the resources set by this quota are part of the result
+    fn sub(self, other: Self) -> Self::Output {
+        self.0
+            .iter()
+            .map(|(resource, quantity)| {
+                let remaining = match other.get(resource) {
+                    Some(used) => *quantity - used,
+                    None => *quantity,
+                };
+                (resource.clone(), remaining)
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&ResourceQuotaLimit> for Quota {
+    type Error = QuantityError;
+
+    fn try_from(limit: &ResourceQuotaLimit) -> Result<Self, Self::Error> {
+        Quota::from_resources(limit)
+    }
+}
+
+impl TryFrom<&ContainerResourceLimit> for Quota {
+    type Error = QuantityError;
+
+    /// The resources are named after the fields of the Rancher object, like
+    /// `requestsCpu` or `limitsMemory`
+    fn try_from(limit: &ContainerResourceLimit) -> Result<Self, Self::Error> {
+        Quota::from_resources(limit)
+    }
+}
+
+impl Quota {
+    /// Build the quota from an object whose fields are the resources, and
+    /// whose values are the quantities
+    fn from_resources<T>(limit: &T) -> Result<Self, QuantityError>
+    where
+        T: serde::Serialize + fmt::Debug,
+    {
+        let resources: BTreeMap<String, String> = serde_json::to_value(limit)
+            .and_then(serde_json::from_value)
+            .map_err(|_| QuantityError {
+                quantity: format!("{limit:?}"),
+                reason: "cannot convert the resource quota limit",
+            })?;
+
+        resources
+            .into_iter()
+            .map(
+                |(resource, quantity)| match quantity.parse::<ParsedQuantity>() {
+                    Ok(parsed) => Ok((resource, parsed)),
+                    Err(e) => Err(QuantityError {
+                        quantity: format!("{resource}={}", e.quantity),
+                        reason: e.reason,
+                    }),
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("0", 0)]
+    #[case("1", NANO)]
+    #[case("+1", NANO)]
+    #[case("-1", -NANO)]
+    #[case("100m", 100_000_000)]
+    #[case("0.5", 500_000_000)]
+    #[case(".5", 500_000_000)]
+    #[case("1.5k", 1_500 * NANO)]
+    #[case("2M", 2_000_000 * NANO)]
+    #[case("1G", 1_000_000_000 * NANO)]
+    #[case("1Ki", 1024 * NANO)]
+    #[case("1Mi", 1024 * 1024 * NANO)]
+    #[case("1.5Gi", 1024 * 1024 * 1024 * NANO * 3 / 2)]
+    #[case("1e3", 1_000 * NANO)]
+    #[case("1E-3", 1_000_000)]
+    #[case("10n", 10)]
+    #[case("1u", 1_000)]
+    #[case("0.1n", 1)]
+    fn parse_quantity(#[case] quantity: &str, #[case] expected_nanos: i128) {
+        assert_eq!(
+            Ok(ParsedQuantity(expected_nanos)),
+            quantity.parse::<ParsedQuantity>()
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("Mi")]
+    #[case("1.2.3")]
+    #[case("1Zi")]
+    #[case("1 Mi")]
+    #[case("1ex")]
+    #[case("1Ei000000000000000000000000000000000")]
+    #[case("1e2147483647")]
+    fn parse_invalid_quantity(#[case] quantity: &str) {
+        assert!(quantity.parse::<ParsedQuantity>().is_err());
+    }
+
+    #[rstest]
+    #[case("2", "2")]
+    #[case("0.5", "500m")]
+    #[case("1Ki", "1Ki")]
+    #[case("2147483648", "2Gi")]
+    #[case("1.5Gi", "1536Mi")]
+    #[case("-2Ki", "-2Ki")]
+    #[case("1000", "1000")]
+    #[case("0", "0")]
+    #[case("1u", "1u")]
+    #[case("3n", "3n")]
+    fn display_quantity(#[case] quantity: &str, #[case] expected: &str) {
+        let parsed: ParsedQuantity = quantity.parse().expect("valid quantity");
+        assert_eq!(expected, parsed.to_string());
+    }
+
+    fn quota(limit: serde_json::Value) -> Quota {
+        let limit: ResourceQuotaLimit =
+            serde_json::from_value(limit).expect("cannot deserialize limit");
+        Quota::try_from(&limit).expect("cannot build quota")
+    }
+
+    #[test]
+    fn not_set_is_different_from_zero() {
+        let limit = quota(json!({"pods": "0"}));
+        assert_eq!(Some(ParsedQuantity(0)), limit.get("pods"));
+        assert_eq!(None, limit.get("services"));
+
+        let requested = quota(json!({"pods": "1", "services": "100"}));
+        let overflows = requested.overflows(&limit);
+        assert_eq!(1, overflows.len());
+        assert_eq!("pods", overflows[0].resource);
+    }
+
+    #[test]
+    fn default_limit_is_empty() {
+        assert!(quota(json!({})).is_empty());
+        assert!(Quota::try_from(&ResourceQuotaLimit::default())
+            .expect("cannot build quota")
+            .is_empty());
+    }
+
+    #[test]
+    fn add_and_sub() {
+        let used = quota(json!({"limitsCpu": "500m", "pods": "2"}));
+        let requested = quota(json!({"limitsCpu": "1", "limitsMemory": "1Gi"}));
+        assert_eq!(
+            quota(json!({"limitsCpu": "1500m", "pods": "2", "limitsMemory": "1Gi"})),
+            &used + &requested
+        );
+
+        let limit = quota(json!({"limitsCpu": "2", "limitsMemory": "4Gi"}));
+        assert_eq!(
+            quota(json!({"limitsCpu": "1500m", "limitsMemory": "4Gi"})),
+            &limit - &used
+        );
+    }
+
+    #[rstest]
+    #[case(json!({"limitsCpu": "1"}), json!({"limitsCpu": "1000m"}), true)]
+    #[case(json!({"limitsCpu": "1001m"}), json!({"limitsCpu": "1"}), false)]
+    #[case(json!({"requestsMemory": "1Gi"}), json!({"requestsMemory": "1G"}), false)]
+    #[case(json!({"requestsMemory": "1G"}), json!({"requestsMemory": "1Gi"}), true)]
+    #[case(json!({"pods": "100"}), json!({}), true)]
+    #[case(json!({}), json!({"pods": "0"}), true)]
+    fn fits_within(
+        #[case] requested: serde_json::Value,
+        #[case] limit: serde_json::Value,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(expected, quota(requested).fits_within(&quota(limit)));
+    }
+
+    #[test]
+    fn extended_resources() {
+        let limit = quota(json!({"pods": "10", "requests.nvidia.com/gpu": "2"}));
+        let requested = quota(json!({"requests.nvidia.com/gpu": "3", "count/jobs.batch": "1"}));
+
+        let overflows = requested.overflows(&limit);
+        assert_eq!(1, overflows.len());
+        assert_eq!("requests.nvidia.com/gpu", overflows[0].resource);
+    }
+
+    #[test]
+    fn invalid_quantity_inside_of_limit() {
+        let limit: ResourceQuotaLimit =
+            serde_json::from_value(json!({"pods": "ten"})).expect("cannot deserialize limit");
+        let err = Quota::try_from(&limit).expect_err("should fail");
+        assert_eq!("pods=ten", err.quantity);
+    }
+}
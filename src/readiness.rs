@@ -0,0 +1,77 @@
+use crate::custom_resources::Project;
+
+/// Conditions set by Rancher once the Project has been fully initialized
+pub(crate) const REQUIRED_PROJECT_CONDITIONS: [&str; 2] =
+    ["BackingNamespaceCreated", "InitialRolesPopulated"];
+
+/// Explain why the Project is not ready to receive new Namespaces. Returns
+/// `None` when the Project is ready.
+///
+/// A Project is not ready while it's being deleted, or while Rancher has not
+/// yet completed its initialization. Projects without a status, like the
+/// ones mirrored from the management cluster, are considered to be ready
+pub(crate) fn not_ready_reason(project: &Project) -> Option<String> {
+    if project.metadata.deletion_timestamp.is_some() {
+        return Some("is being deleted".to_string());
+    }
+
+    let status = project.status.as_ref()?;
+    let pending: Vec<&str> = REQUIRED_PROJECT_CONDITIONS
+        .into_iter()
+        .filter(|type_| {
+            !status
+                .condition(type_)
+                .is_some_and(|condition| condition.is_true())
+        })
+        .collect();
+    if pending.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "is still being initialized, pending conditions: {}",
+        pending.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case::no_status(json!({}), None)]
+    #[case::ready(
+        json!({"status": {"conditions": [
+            {"type": "BackingNamespaceCreated", "status": "True"},
+            {"type": "CreatorMadeOwner", "status": "True"},
+            {"type": "InitialRolesPopulated", "status": "True"},
+        ]}}),
+        None
+    )]
+    #[case::initializing(
+        json!({"status": {"conditions": [
+            {"type": "BackingNamespaceCreated", "status": "True"},
+            {"type": "InitialRolesPopulated", "status": "Unknown"},
+        ]}}),
+        Some("is still being initialized, pending conditions: InitialRolesPopulated")
+    )]
+    #[case::no_conditions(
+        json!({"status": {}}),
+        Some("is still being initialized, pending conditions: BackingNamespaceCreated, InitialRolesPopulated")
+    )]
+    #[case::deleted(
+        json!({
+            "metadata": {"deletionTimestamp": "2024-05-02T14:40:19Z"},
+            "status": {"conditions": [
+                {"type": "BackingNamespaceCreated", "status": "True"},
+                {"type": "InitialRolesPopulated", "status": "True"},
+            ]},
+        }),
+        Some("is being deleted")
+    )]
+    fn readiness(#[case] project: serde_json::Value, #[case] expected: Option<&str>) {
+        let project: Project = serde_json::from_value(project).expect("cannot deserialize Project");
+        assert_eq!(expected, not_ready_reason(&project).as_deref());
+    }
+}
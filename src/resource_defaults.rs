@@ -0,0 +1,216 @@
+use k8s_openapi::api::core::v1 as apicore;
+
+use crate::custom_resources::Project;
+use crate::errors::{PolicyError, PolicyResult};
+
+/// Annotation used by Rancher to define the resource quota of a Namespace
+pub(crate) const RANCHER_RESOURCE_QUOTA_ANNOTATION: &str = "field.cattle.io/resourceQuota";
+
+/// Annotation used by Rancher to define the default resource requests and
+/// limits of the containers created inside of a Namespace
+pub(crate) const RANCHER_CONTAINER_DEFAULT_RESOURCE_LIMIT_ANNOTATION: &str =
+    "field.cattle.io/containerDefaultResourceLimit";
+
+/// Set the resource quota annotation of the Namespace, using the default
+/// Namespace quota of the Project. Namespaces that already have the annotation
+/// are left untouched
+pub(crate) fn inject_namespace_resource_quota(
+    project: &Project,
+    namespace: &mut apicore::Namespace,
+) -> PolicyResult<()> {
+    if namespace
+        .metadata
+        .annotations
+        .as_ref()
+        .is_some_and(|annotations| annotations.contains_key(RANCHER_RESOURCE_QUOTA_ANNOTATION))
+    {
+        return Ok(());
+    }
+
+    let default_quota = match project
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.namespace_default_resource_quota.as_ref())
+    {
+        Some(quota) if quota.limit.as_ref().is_some_and(|limit| !limit.is_empty()) => quota,
+        _ => return Ok(()),
+    };
+
+    let value = serde_json::to_string(default_quota).map_err(|e| PolicyError::Serialization {
+        what: "the default Namespace resource quota of the Project".to_string(),
+        details: e.to_string(),
+    })?;
+    namespace
+        .metadata
+        .annotations
+        .get_or_insert_with(Default::default)
+        .insert(RANCHER_RESOURCE_QUOTA_ANNOTATION.to_string(), value);
+
+    Ok(())
+}
+
+/// Set the container default resource limit annotation of the Namespace, using
+/// the value defined by the Project. The value already set by the user is
+/// kept unless `overwrite` is true
+pub(crate) fn inject_container_default_resource_limit(
+    project: &Project,
+    namespace: &mut apicore::Namespace,
+    overwrite: bool,
+) -> PolicyResult<()> {
+    if !overwrite
+        && namespace
+            .metadata
+            .annotations
+            .as_ref()
+            .is_some_and(|annotations| {
+                annotations.contains_key(RANCHER_CONTAINER_DEFAULT_RESOURCE_LIMIT_ANNOTATION)
+            })
+    {
+        return Ok(());
+    }
+
+    let default_limit = match project
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.container_default_resource_limit.as_ref())
+    {
+        Some(limit) if !limit.is_empty() => limit,
+        _ => return Ok(()),
+    };
+
+    let value = serde_json::to_string(default_limit).map_err(|e| PolicyError::Serialization {
+        what: "the container default resource limit of the Project".to_string(),
+        details: e.to_string(),
+    })?;
+    namespace
+        .metadata
+        .annotations
+        .get_or_insert_with(Default::default)
+        .insert(
+            RANCHER_CONTAINER_DEFAULT_RESOURCE_LIMIT_ANNOTATION.to_string(),
+            value,
+        );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    fn project_with_spec(spec: serde_json::Value) -> Project {
+        serde_json::from_value(json!({
+            "apiVersion": "management.cattle.io/v3",
+            "kind": "Project",
+            "metadata": {"name": "p-abc12", "namespace": "local"},
+            "spec": spec,
+        }))
+        .expect("cannot deserialize Project")
+    }
+
+    #[rstest]
+    #[case(
+        json!({"limit": {"limitsCpu": "100m", "configMaps": "10"}}),
+        None,
+        Some(r#"{"limit":{"configMaps":"10","limitsCpu":"100m"}}"#)
+    )]
+    #[case(
+        json!({"limit": {"limitsCpu": "100m"}}),
+        Some(r#"{"limit":{"limitsCpu":"1"}}"#),
+        Some(r#"{"limit":{"limitsCpu":"1"}}"#)
+    )]
+    #[case(json!({"limit": {}}), None, None)]
+    #[case(json!(null), None, None)]
+    fn inject_quota(
+        #[case] default_quota: serde_json::Value,
+        #[case] current: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let project = project_with_spec(json!({
+            "description": "",
+            "enableProjectMonitoring": false,
+            "namespaceDefaultResourceQuota": default_quota,
+        }));
+        let mut namespace = apicore::Namespace::default();
+        if let Some(current) = current {
+            namespace.metadata.annotations = Some(
+                [(
+                    RANCHER_RESOURCE_QUOTA_ANNOTATION.to_string(),
+                    current.to_string(),
+                )]
+                .into(),
+            );
+        }
+
+        inject_namespace_resource_quota(&project, &mut namespace).expect("should not fail");
+
+        assert_eq!(
+            expected,
+            namespace
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(RANCHER_RESOURCE_QUOTA_ANNOTATION))
+                .map(|v| v.as_str())
+        );
+    }
+
+    #[rstest]
+    #[case(
+        json!({"limitsCpu": "20m", "requestsCpu": "1m"}),
+        None,
+        false,
+        Some(r#"{"requestsCpu":"1m","limitsCpu":"20m"}"#)
+    )]
+    #[case(
+        json!({"limitsCpu": "20m"}),
+        Some(r#"{"limitsCpu":"1"}"#),
+        false,
+        Some(r#"{"limitsCpu":"1"}"#)
+    )]
+    #[case(
+        json!({"limitsCpu": "20m"}),
+        Some(r#"{"limitsCpu":"1"}"#),
+        true,
+        Some(r#"{"limitsCpu":"20m"}"#)
+    )]
+    #[case(json!({}), Some(r#"{"limitsCpu":"1"}"#), true, Some(r#"{"limitsCpu":"1"}"#))]
+    #[case(json!({}), None, false, None)]
+    fn inject_container_limit(
+        #[case] default_limit: serde_json::Value,
+        #[case] current: Option<&str>,
+        #[case] overwrite: bool,
+        #[case] expected: Option<&str>,
+    ) {
+        let project = project_with_spec(json!({
+            "description": "",
+            "enableProjectMonitoring": false,
+            "containerDefaultResourceLimit": default_limit,
+        }));
+        let mut namespace = apicore::Namespace::default();
+        if let Some(current) = current {
+            namespace.metadata.annotations = Some(
+                [(
+                    RANCHER_CONTAINER_DEFAULT_RESOURCE_LIMIT_ANNOTATION.to_string(),
+                    current.to_string(),
+                )]
+                .into(),
+            );
+        }
+
+        inject_container_default_resource_limit(&project, &mut namespace, overwrite)
+            .expect("should not fail");
+
+        assert_eq!(
+            expected,
+            namespace
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(RANCHER_CONTAINER_DEFAULT_RESOURCE_LIMIT_ANNOTATION))
+                .map(|v| v.as_str())
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, LabelSelectorRequirement};
+use std::collections::BTreeMap;
+
+/// Returns true when the labels match the selector, following the Kubernetes
+/// semantics: all the `matchLabels` and all the `matchExpressions` must be
+/// satisfied. An empty selector matches everything.
+///
+/// The selector must have been validated with [`validate_selector`]
+pub(crate) fn selector_matches(
+    selector: &LabelSelector,
+    labels: &BTreeMap<String, String>,
+) -> bool {
+    let labels_match = selector
+        .match_labels
+        .iter()
+        .flatten()
+        .all(|(key, value)| labels.get(key) == Some(value));
+    let expressions_match = selector
+        .match_expressions
+        .iter()
+        .flatten()
+        .all(|requirement| requirement_matches(requirement, labels));
+    labels_match && expressions_match
+}
+
+fn requirement_matches(
+    requirement: &LabelSelectorRequirement,
+    labels: &BTreeMap<String, String>,
+) -> bool {
+    let value = labels.get(&requirement.key);
+    let values = requirement.values.as_deref().unwrap_or_default();
+    match requirement.operator.as_str() {
+        "In" => value.is_some_and(|value| values.contains(value)),
+        "NotIn" => !value.is_some_and(|value| values.contains(value)),
+        "Exists" => value.is_some(),
+        "DoesNotExist" => value.is_none(),
+        _ => false,
+    }
+}
+
+/// Ensure the operators of the selector are known, and that they are used
+/// with the right number of values
+pub(crate) fn validate_selector(selector: &LabelSelector) -> Result<(), String> {
+    for requirement in selector.match_expressions.iter().flatten() {
+        let has_values = requirement
+            .values
+            .as_ref()
+            .is_some_and(|values| !values.is_empty());
+        match requirement.operator.as_str() {
+            "In" | "NotIn" if !has_values => {
+                return Err(format!(
+                    "the `{}` operator of the `{}` expression requires some values",
+                    requirement.operator, requirement.key
+                ))
+            }
+            "Exists" | "DoesNotExist" if has_values => {
+                return Err(format!(
+                    "the `{}` operator of the `{}` expression does not accept values",
+                    requirement.operator, requirement.key
+                ))
+            }
+            "In" | "NotIn" | "Exists" | "DoesNotExist" => {}
+            operator => {
+                return Err(format!(
+                    "unknown operator `{operator}` of the `{}` expression",
+                    requirement.key
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case::empty(json!({}), json!({}), true)]
+    #[case::match_labels(json!({"matchLabels": {"propagation-beta": "true"}}), json!({"propagation-beta": "true", "a": "b"}), true)]
+    #[case::match_labels_wrong_value(json!({"matchLabels": {"propagation-beta": "true"}}), json!({"propagation-beta": "false"}), false)]
+    #[case::match_labels_missing(json!({"matchLabels": {"propagation-beta": "true"}}), json!({}), false)]
+    #[case::in_(json!({"matchExpressions": [{"key": "env", "operator": "In", "values": ["dev", "test"]}]}), json!({"env": "dev"}), true)]
+    #[case::in_missing(json!({"matchExpressions": [{"key": "env", "operator": "In", "values": ["dev"]}]}), json!({}), false)]
+    #[case::not_in(json!({"matchExpressions": [{"key": "env", "operator": "NotIn", "values": ["prod"]}]}), json!({"env": "dev"}), true)]
+    #[case::not_in_missing(json!({"matchExpressions": [{"key": "env", "operator": "NotIn", "values": ["prod"]}]}), json!({}), true)]
+    #[case::not_in_excluded(json!({"matchExpressions": [{"key": "env", "operator": "NotIn", "values": ["prod"]}]}), json!({"env": "prod"}), false)]
+    #[case::exists(json!({"matchExpressions": [{"key": "env", "operator": "Exists"}]}), json!({"env": ""}), true)]
+    #[case::does_not_exist(json!({"matchExpressions": [{"key": "env", "operator": "DoesNotExist"}]}), json!({"env": "dev"}), false)]
+    #[case::all_must_match(
+        json!({
+            "matchLabels": {"propagation-beta": "true"},
+            "matchExpressions": [{"key": "env", "operator": "In", "values": ["dev"]}],
+        }),
+        json!({"propagation-beta": "true", "env": "prod"}),
+        false
+    )]
+    fn matches(
+        #[case] selector: serde_json::Value,
+        #[case] labels: serde_json::Value,
+        #[case] expected: bool,
+    ) {
+        let selector: LabelSelector =
+            serde_json::from_value(selector).expect("cannot deserialize selector");
+        let labels: BTreeMap<String, String> =
+            serde_json::from_value(labels).expect("cannot deserialize labels");
+
+        assert_eq!(expected, selector_matches(&selector, &labels));
+    }
+
+    #[rstest]
+    #[case(json!({"matchExpressions": [{"key": "env", "operator": "In", "values": ["dev"]}]}), true)]
+    #[case(json!({"matchExpressions": [{"key": "env", "operator": "In"}]}), false)]
+    #[case(json!({"matchExpressions": [{"key": "env", "operator": "NotIn", "values": []}]}), false)]
+    #[case(json!({"matchExpressions": [{"key": "env", "operator": "Exists"}]}), true)]
+    #[case(json!({"matchExpressions": [{"key": "env", "operator": "Exists", "values": ["dev"]}]}), false)]
+    #[case(json!({"matchExpressions": [{"key": "env", "operator": "Equals", "values": ["dev"]}]}), false)]
+    fn validate(#[case] selector: serde_json::Value, #[case] valid: bool) {
+        let selector: LabelSelector =
+            serde_json::from_value(selector).expect("cannot deserialize selector");
+        assert_eq!(valid, validate_selector(&selector).is_ok());
+    }
+}
@@ -1,20 +1,551 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kubewarden::request::UserInfo;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+use crate::glob::{glob_match, is_glob};
+use crate::labels::{validate_label_key, validate_label_value};
+use crate::project_id::{validate_cluster_id, ProjectId, LOCAL_CLUSTER_ID};
+use crate::selector::validate_selector;
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum FailureMode {
     #[default]
     Ignore,
     Fail,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+/// What to do when a request violates a rule of the policy
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EnforcementMode {
+    /// Reject the request
+    #[default]
+    Reject,
+    /// Accept the request, but return a warning to the user
+    Warn,
+}
+
+/// Location of the ConfigMap that mirrors, inside of a downstream cluster,
+/// the labels of the Projects defined inside of the management cluster
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ProjectsMirror {
+    pub namespace: String,
+    pub name: String,
+}
+
+/// Users, groups and ServiceAccounts that are exempted from a rule
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Subjects {
+    pub users: Vec<String>,
+    pub groups: Vec<String>,
+    /// ServiceAccounts, in the `<namespace>:<name>` format
+    pub service_accounts: Vec<String>,
+}
+
+impl Subjects {
+    /// Returns true when the user that performed the request is one of the
+    /// subjects
+    pub fn contains(&self, user_info: &UserInfo) -> bool {
+        self.users.contains(&user_info.username)
+            || self
+                .groups
+                .iter()
+                .any(|group| user_info.groups.contains(group))
+            || self.service_accounts.iter().any(|service_account| {
+                user_info.username == format!("system:serviceaccount:{service_account}")
+            })
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(service_account) = self.service_accounts.iter().find(|service_account| {
+            !service_account
+                .split_once(':')
+                .is_some_and(|(namespace, name)| is_dns_label(namespace) && is_dns_subdomain(name))
+        }) {
+            return Err(format!(
+                "`{service_account}` is not a ServiceAccount in the `<namespace>:<name>` format"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Requests that are accepted without being evaluated by the policy
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Exemptions {
+    /// The users, groups and ServiceAccounts whose requests are exempted
+    #[serde(flatten)]
+    pub subjects: Subjects,
+    /// Names of the exempted Namespaces, glob patterns are allowed
+    pub namespaces: Vec<String>,
+}
+
+impl Exemptions {
+    /// Explain why the request is exempted, `None` when it's not exempted
+    pub fn reason(&self, user_info: &UserInfo, namespace: Option<&str>) -> Option<String> {
+        if self.subjects.contains(user_info) {
+            return Some(format!("user `{}` is exempted", user_info.username));
+        }
+        let namespace = namespace?;
+        self.namespaces
+            .iter()
+            .find(|pattern| glob_match(pattern, namespace))
+            .map(|pattern| format!("Namespace `{namespace}` matches the exempted `{pattern}`"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(default)]
 pub(crate) struct Settings {
     pub downstream_cluster_failure_mode: FailureMode,
+    /// Failure mode to be used for specific downstream clusters. The keys are
+    /// cluster ids or glob patterns matching them. Clusters that are not
+    /// matched by any key use `downstream_cluster_failure_mode`
+    pub downstream_cluster_failure_modes: BTreeMap<String, FailureMode>,
+    /// The id of the cluster where the policy is running. Namespaces belonging to
+    /// Projects of other clusters are considered to be part of a downstream cluster
+    pub local_cluster_id: String,
+    /// The Namespace where the Project objects of the local cluster are defined.
+    /// Defaults to the id of the local cluster
+    pub local_cluster_projects_namespace: Option<String>,
+    /// The ConfigMap holding the labels of the Projects of a downstream cluster
+    pub downstream_projects_mirror: Option<ProjectsMirror>,
+    /// What to do when the Project referenced by a Namespace belongs to a
+    /// cluster other than the one written inside of the Namespace annotation
+    pub project_cluster_mismatch_mode: EnforcementMode,
+    /// What to do when a Namespace joins a Project that is still being
+    /// initialized by Rancher, or that is being deleted
+    pub project_not_ready_mode: EnforcementMode,
+    /// Add the `field.cattle.io/resourceQuota` annotation to the Namespaces that
+    /// do not have it, using the default Namespace quota of their Project
+    pub inject_namespace_resource_quota: bool,
+    /// Add the `field.cattle.io/containerDefaultResourceLimit` annotation to the
+    /// Namespaces, using the container default resource limit of their Project
+    pub inject_container_default_resource_limit: bool,
+    /// Replace the `field.cattle.io/containerDefaultResourceLimit` annotation
+    /// already set by the user
+    pub overwrite_container_default_resource_limit: bool,
+    /// Reject the Namespaces whose `field.cattle.io/resourceQuota` annotation
+    /// requests more resources than the ones still available to their Project
+    pub enforce_project_resource_quota: bool,
+    /// Label domains, in addition to `kubernetes.io` and `k8s.io`, that cannot
+    /// be propagated to the Namespaces. Subdomains are reserved too
+    pub reserved_label_domains: Vec<String>,
+    /// The values allowed for the propagated labels. The keys are the label
+    /// keys, without the `propagate.` prefix
+    pub allowed_label_values: BTreeMap<String, Vec<String>>,
+    /// On Project UPDATE, look for the Namespaces whose labels would no
+    /// longer match the ones propagated by the Project
+    pub analyze_project_label_changes: bool,
+    /// What to do when a Project change leaves some of its Namespaces with
+    /// outdated labels
+    pub project_label_drift_mode: EnforcementMode,
+    /// The maximum number of Namespaces a Project can have. Projects can
+    /// override it with the `project.kubewarden.io/max-namespaces` annotation
+    pub max_namespaces_per_project: Option<u32>,
+    /// Reject the Namespace UPDATEs that change or remove a propagated label,
+    /// instead of silently restoring it
+    pub immutable_propagated_labels: bool,
+    /// The subjects allowed to change or remove the propagated labels
+    pub immutable_propagated_labels_exemptions: Subjects,
+    /// Requests accepted unchanged, without being evaluated
+    pub exemptions: Exemptions,
+    /// Evaluate only the Namespaces whose labels match this selector. All the
+    /// Namespaces are evaluated when not set
+    pub namespace_selector: Option<LabelSelector>,
+    /// The Project, in the `<cluster id>:<project id>` format, the Namespaces
+    /// created without a Project are assigned to
+    pub default_project: Option<String>,
+    /// Names of the Namespaces that are not assigned to the default Project,
+    /// glob patterns are allowed
+    pub default_project_exclusions: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            downstream_cluster_failure_mode: FailureMode::default(),
+            downstream_cluster_failure_modes: BTreeMap::new(),
+            local_cluster_id: LOCAL_CLUSTER_ID.to_string(),
+            local_cluster_projects_namespace: None,
+            downstream_projects_mirror: None,
+            project_cluster_mismatch_mode: EnforcementMode::default(),
+            project_not_ready_mode: EnforcementMode::Warn,
+            inject_namespace_resource_quota: false,
+            inject_container_default_resource_limit: false,
+            overwrite_container_default_resource_limit: false,
+            enforce_project_resource_quota: false,
+            reserved_label_domains: Vec::new(),
+            allowed_label_values: BTreeMap::new(),
+            analyze_project_label_changes: false,
+            project_label_drift_mode: EnforcementMode::Warn,
+            max_namespaces_per_project: None,
+            immutable_propagated_labels: false,
+            immutable_propagated_labels_exemptions: Subjects::default(),
+            exemptions: Exemptions::default(),
+            namespace_selector: None,
+            default_project: None,
+            default_project_exclusions: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// The Namespace where the Project objects of the local cluster are defined
+    pub fn projects_namespace(&self) -> &str {
+        self.local_cluster_projects_namespace
+            .as_deref()
+            .unwrap_or(&self.local_cluster_id)
+    }
+
+    /// Find the failure mode to be used for the given downstream cluster.
+    /// Returns the failure mode and the rule that selected it.
+    ///
+    /// An exact match of the cluster id wins over glob patterns. When more
+    /// patterns match, the most specific one, the one with more literal
+    /// characters, is used
+    pub fn downstream_failure_mode(&self, cluster_id: &str) -> (FailureMode, &str) {
+        if let Some((rule, mode)) = self
+            .downstream_cluster_failure_modes
+            .get_key_value(cluster_id)
+        {
+            return (*mode, rule);
+        }
+
+        self.downstream_cluster_failure_modes
+            .iter()
+            .filter(|(pattern, _)| is_glob(pattern) && glob_match(pattern, cluster_id))
+            .max_by_key(|(pattern, _)| {
+                (
+                    pattern.chars().filter(|c| *c != '*' && *c != '?').count(),
+                    std::cmp::Reverse(*pattern),
+                )
+            })
+            .map(|(pattern, mode)| (*mode, pattern.as_str()))
+            .unwrap_or((
+                self.downstream_cluster_failure_mode,
+                "downstream_cluster_failure_mode",
+            ))
+    }
+
+    /// The Project the given Namespace, created without a Project, must be
+    /// assigned to. `None` when there's no default Project, or when the
+    /// Namespace is excluded
+    pub fn default_project_for(&self, namespace: &str) -> Option<ProjectId> {
+        let default_project = self.default_project.as_ref()?;
+        if self
+            .default_project_exclusions
+            .iter()
+            .any(|pattern| glob_match(pattern, namespace))
+        {
+            return None;
+        }
+        // the value is checked when the settings are validated
+        default_project.parse().ok()
+    }
+}
+
+/// Returns true when `name` is a valid RFC 1123 label, the format
+/// required by Namespace names
+pub(crate) fn is_dns_label(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 63
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+/// Returns true when `name` is a valid RFC 1123 subdomain, the format
+/// required by the names of most of the Kubernetes resources
+pub(crate) fn is_dns_subdomain(name: &str) -> bool {
+    name.len() <= 253 && name.split('.').all(is_dns_label)
 }
 
 impl kubewarden::settings::Validatable for Settings {
     fn validate(&self) -> Result<(), String> {
+        validate_cluster_id(&self.local_cluster_id)
+            .map_err(|e| format!("invalid local_cluster_id: {e}"))?;
+
+        if self
+            .downstream_cluster_failure_modes
+            .keys()
+            .any(|pattern| pattern.is_empty())
+        {
+            return Err(
+                "invalid downstream_cluster_failure_modes: cluster ids cannot be empty".to_string(),
+            );
+        }
+
+        if let Some(namespace) = &self.local_cluster_projects_namespace {
+            if !is_dns_label(namespace) {
+                return Err(format!(
+                    "invalid local_cluster_projects_namespace: `{namespace}` is not a valid Namespace name"
+                ));
+            }
+        }
+
+        if self.overwrite_container_default_resource_limit
+            && !self.inject_container_default_resource_limit
+        {
+            return Err(
+                "overwrite_container_default_resource_limit requires inject_container_default_resource_limit to be enabled".to_string(),
+            );
+        }
+
+        if let Some(mirror) = &self.downstream_projects_mirror {
+            if !is_dns_label(&mirror.namespace) {
+                return Err(format!(
+                    "invalid downstream_projects_mirror: `{}` is not a valid Namespace name",
+                    mirror.namespace
+                ));
+            }
+            if !is_dns_subdomain(&mirror.name) {
+                return Err(format!(
+                    "invalid downstream_projects_mirror: `{}` is not a valid ConfigMap name",
+                    mirror.name
+                ));
+            }
+        }
+
+        if let Some(domain) = self
+            .reserved_label_domains
+            .iter()
+            .find(|domain| !is_dns_subdomain(domain))
+        {
+            return Err(format!(
+                "invalid reserved_label_domains: `{domain}` is not a valid DNS subdomain"
+            ));
+        }
+
+        for (key, values) in &self.allowed_label_values {
+            validate_label_key(key)
+                .map_err(|e| format!("invalid allowed_label_values: `{key}`: {e}"))?;
+            for value in values {
+                validate_label_value(value).map_err(|e| {
+                    format!("invalid allowed_label_values: `{key}`: `{value}`: {e}")
+                })?;
+            }
+        }
+
+        self.immutable_propagated_labels_exemptions
+            .validate()
+            .map_err(|e| format!("invalid immutable_propagated_labels_exemptions: {e}"))?;
+        self.exemptions
+            .subjects
+            .validate()
+            .map_err(|e| format!("invalid exemptions: {e}"))?;
+        if self
+            .exemptions
+            .namespaces
+            .iter()
+            .any(|pattern| pattern.is_empty())
+        {
+            return Err("invalid exemptions: Namespace names cannot be empty".to_string());
+        }
+
+        if let Some(default_project) = &self.default_project {
+            default_project
+                .parse::<ProjectId>()
+                .map_err(|e| format!("invalid default_project: {e}"))?;
+        }
+        if self
+            .default_project_exclusions
+            .iter()
+            .any(|pattern| pattern.is_empty())
+        {
+            return Err(
+                "invalid default_project_exclusions: Namespace names cannot be empty".to_string(),
+            );
+        }
+
+        if let Some(selector) = &self.namespace_selector {
+            validate_selector(selector).map_err(|e| format!("invalid namespace_selector: {e}"))?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kubewarden::settings::Validatable;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(json!({}), true)]
+    #[case(json!({"local_cluster_id": "c-m-abcd1234"}), true)]
+    #[case(json!({"local_cluster_id": "management"}), false)]
+    #[case(json!({"local_cluster_projects_namespace": "c-m-abcd1234"}), true)]
+    #[case(json!({"local_cluster_projects_namespace": "Not_A_Namespace"}), false)]
+    #[case(json!({"downstream_cluster_failure_modes": {"c-m-*": "Fail"}}), true)]
+    #[case(json!({"downstream_cluster_failure_modes": {"": "Fail"}}), false)]
+    #[case(json!({"overwrite_container_default_resource_limit": true}), false)]
+    #[case(
+        json!({
+            "inject_container_default_resource_limit": true,
+            "overwrite_container_default_resource_limit": true,
+        }),
+        true
+    )]
+    #[case(
+        json!({"downstream_projects_mirror": {"namespace": "cattle-system", "name": "project-labels"}}),
+        true
+    )]
+    #[case(
+        json!({"downstream_projects_mirror": {"namespace": "cattle-system", "name": "Project_Labels"}}),
+        false
+    )]
+    #[case(json!({"reserved_label_domains": ["example.com"]}), true)]
+    #[case(json!({"reserved_label_domains": ["Example.com"]}), false)]
+    #[case(json!({"allowed_label_values": {"example.com/tier": ["gold", "silver"]}}), true)]
+    #[case(json!({"allowed_label_values": {"tier": ["gold silver"]}}), false)]
+    #[case(json!({"allowed_label_values": {"-tier": ["gold"]}}), false)]
+    #[case(
+        json!({"immutable_propagated_labels_exemptions": {"service_accounts": ["cattle-system:rancher"]}}),
+        true
+    )]
+    #[case(
+        json!({"immutable_propagated_labels_exemptions": {"service_accounts": ["rancher"]}}),
+        false
+    )]
+    #[case(json!({"exemptions": {"namespaces": ["kube-*"], "users": ["admin"]}}), true)]
+    #[case(json!({"exemptions": {"namespaces": [""]}}), false)]
+    #[case(json!({"exemptions": {"service_accounts": ["rancher"]}}), false)]
+    #[case(json!({"namespace_selector": {"matchLabels": {"propagation-beta": "true"}}}), true)]
+    #[case(
+        json!({"namespace_selector": {"matchExpressions": [{"key": "env", "operator": "Equals"}]}}),
+        false
+    )]
+    #[case(json!({"default_project": "local:p-abc12"}), true)]
+    #[case(json!({"default_project": "p-abc12"}), false)]
+    #[case(json!({"default_project": "local:p-abc12", "default_project_exclusions": [""]}), false)]
+    fn validate_settings(#[case] settings: serde_json::Value, #[case] valid: bool) {
+        let settings: Settings =
+            serde_json::from_value(settings).expect("cannot deserialize settings");
+        assert_eq!(valid, settings.validate().is_ok());
+    }
+
+    #[rstest]
+    #[case::user("alice", &[], true)]
+    #[case::group("bob", &["platform-admins"], true)]
+    #[case::service_account("system:serviceaccount:cattle-system:rancher", &[], true)]
+    #[case::other_service_account("system:serviceaccount:default:rancher", &[], false)]
+    #[case::nobody("bob", &["developers"], false)]
+    fn subjects(#[case] username: &str, #[case] groups: &[&str], #[case] expected: bool) {
+        let subjects: Subjects = serde_json::from_value(json!({
+            "users": ["alice"],
+            "groups": ["platform-admins"],
+            "service_accounts": ["cattle-system:rancher"],
+        }))
+        .expect("cannot deserialize subjects");
+        let user_info = UserInfo {
+            username: username.to_string(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(expected, subjects.contains(&user_info));
+    }
+
+    #[rstest]
+    #[case::user("admin", None, Some("user `admin` is exempted"))]
+    #[case::service_account(
+        "system:serviceaccount:cattle-system:rancher",
+        Some("team-a"),
+        Some("user `system:serviceaccount:cattle-system:rancher` is exempted")
+    )]
+    #[case::namespace(
+        "bob",
+        Some("kube-system"),
+        Some("Namespace `kube-system` matches the exempted `kube-*`")
+    )]
+    #[case::not_exempted("bob", Some("team-a"), None)]
+    #[case::no_namespace("bob", None, None)]
+    fn exemptions(
+        #[case] username: &str,
+        #[case] namespace: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let exemptions: Exemptions = serde_json::from_value(json!({
+            "users": ["admin"],
+            "service_accounts": ["cattle-system:rancher"],
+            "namespaces": ["kube-*", "cattle-*"],
+        }))
+        .expect("cannot deserialize exemptions");
+        let user_info = UserInfo {
+            username: username.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            expected,
+            exemptions.reason(&user_info, namespace).as_deref()
+        );
+    }
+
+    #[rstest]
+    #[case::assigned(json!({"default_project": "local:p-abc12"}), "team-a", Some("local:p-abc12"))]
+    #[case::no_default(json!({}), "team-a", None)]
+    #[case::excluded(
+        json!({"default_project": "local:p-abc12", "default_project_exclusions": ["kube-*"]}),
+        "kube-public",
+        None
+    )]
+    fn default_project_for(
+        #[case] settings: serde_json::Value,
+        #[case] namespace: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let settings: Settings =
+            serde_json::from_value(settings).expect("cannot deserialize settings");
+        assert_eq!(
+            expected.map(|id| id.parse::<ProjectId>().expect("valid project id")),
+            settings.default_project_for(namespace)
+        );
+    }
+
+    #[rstest]
+    #[case(json!({}), "local")]
+    #[case(json!({"local_cluster_id": "c-m-abcd1234"}), "c-m-abcd1234")]
+    #[case(
+        json!({"local_cluster_id": "c-m-abcd1234", "local_cluster_projects_namespace": "local"}),
+        "local"
+    )]
+    fn projects_namespace(#[case] settings: serde_json::Value, #[case] expected: &str) {
+        let settings: Settings =
+            serde_json::from_value(settings).expect("cannot deserialize settings");
+        assert_eq!(expected, settings.projects_namespace());
+    }
+
+    #[rstest]
+    #[case("c-m-prod1234", FailureMode::Fail, "c-m-prod1234")]
+    #[case("c-m-prod5678", FailureMode::Ignore, "c-m-prod*")]
+    #[case("c-m-abcd1234", FailureMode::Fail, "c-m-*")]
+    #[case("c-abcde", FailureMode::Ignore, "downstream_cluster_failure_mode")]
+    fn downstream_failure_mode(
+        #[case] cluster_id: &str,
+        #[case] expected_mode: FailureMode,
+        #[case] expected_rule: &str,
+    ) {
+        let settings: Settings = serde_json::from_value(json!({
+            "downstream_cluster_failure_mode": "Ignore",
+            "downstream_cluster_failure_modes": {
+                "c-m-*": "Fail",
+                "c-m-prod*": "Ignore",
+                "c-m-prod1234": "Fail",
+            }
+        }))
+        .expect("cannot deserialize settings");
+
+        assert_eq!(
+            (expected_mode, expected_rule),
+            settings.downstream_failure_mode(cluster_id)
+        );
+    }
+}
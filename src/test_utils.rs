@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// The messages an error is expected to contain
+pub(crate) trait ExpectedMessages: fmt::Debug {
+    fn messages(&self) -> Vec<&str>;
+}
+
+impl ExpectedMessages for &str {
+    fn messages(&self) -> Vec<&str> {
+        vec![self]
+    }
+}
+
+impl ExpectedMessages for Vec<&str> {
+    fn messages(&self) -> Vec<&str> {
+        self.clone()
+    }
+}
+
+/// Assert the outcome of a check: `Ok` values must be equal, errors must
+/// contain all the expected messages
+#[track_caller]
+pub(crate) fn assert_outcome<T, E>(result: Result<T, E>, expected: Result<T, impl ExpectedMessages>)
+where
+    T: fmt::Debug + PartialEq,
+    E: fmt::Display + fmt::Debug,
+{
+    match (result, expected) {
+        (Ok(actual), Ok(expected)) => assert_eq!(expected, actual),
+        (Err(err), Err(expected)) => {
+            let msg = err.to_string();
+            for expected in expected.messages() {
+                assert!(msg.contains(expected), "{msg}");
+            }
+        }
+        (result, expected) => panic!("expected {expected:?}, got {result:?}"),
+    }
+}